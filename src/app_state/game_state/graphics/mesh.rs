@@ -2,33 +2,62 @@ use std::sync::Arc;
 
 use wgpu::util::DeviceExt;
 
+use crate::world::mesh_data::MeshData;
+
 use super::vertex::Vertex;
 
 pub struct Mesh {
     size: u32,
     buffer: wgpu::Buffer,
+    index_buffer: Option<wgpu::Buffer>,
 }
 
 impl Mesh {
-    pub fn new(vertex: Vec<Vertex>, device: &Arc<wgpu::Device>) -> Self {
+    /// Uploads `mesh_data` to the GPU, with an index buffer if it has indices.
+    pub fn new(mesh_data: &MeshData, device: &Arc<wgpu::Device>) -> Self {
+        let vertex = Vertex::from_mesh_data(mesh_data);
+
         let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
             contents: bytemuck::cast_slice(vertex.as_slice()),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
+        let index_buffer = mesh_data.indices.as_ref().map(|indices| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: bytemuck::cast_slice(indices.as_slice()),
+                usage: wgpu::BufferUsages::INDEX,
+            })
+        });
+
+        let size = match &mesh_data.indices {
+            Some(indices) => indices.len(),
+            None => vertex.len(),
+        };
+
         Self {
             buffer,
-            size: vertex.len() as u32,
+            index_buffer,
+            size: size as u32,
         }
     }
 
     pub fn draw<'a>(self: &'a Self, render_pass: &mut wgpu::RenderPass<'a>) {
         render_pass.set_vertex_buffer(0, self.buffer.slice(..));
-        render_pass.draw(0..self.size, 0..1);
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..self.size, 0, 0..1);
+            }
+            None => render_pass.draw(0..self.size, 0..1),
+        }
     }
 
     pub fn destroy(&self) {
         self.buffer.destroy();
+        if let Some(index_buffer) = &self.index_buffer {
+            index_buffer.destroy();
+        }
     }
 }
@@ -1,3 +1,5 @@
+use crate::world::mesh_data::MeshData;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
@@ -7,6 +9,21 @@ pub struct Vertex {
 }
 
 impl Vertex {
+    /// Interleaves the attributes of `mesh_data` into a vertex buffer layout.
+    pub fn from_mesh_data(mesh_data: &MeshData) -> Vec<Self> {
+        mesh_data
+            .positions
+            .iter()
+            .zip(mesh_data.normals.iter())
+            .zip(mesh_data.colors.iter())
+            .map(|((position, normal), color)| Self {
+                position: *position,
+                normal: *normal,
+                color: *color,
+            })
+            .collect()
+    }
+
     pub fn get_description<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
@@ -1,7 +1,10 @@
 use strum::EnumCount;
 use strum_macros::{EnumCount as EnumCountMacro, EnumIter};
 use winit::{
-    event::{DeviceEvent, ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+    event::{
+        DeviceEvent, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode,
+        WindowEvent,
+    },
     window::Window,
 };
 
@@ -9,6 +12,9 @@ use self::mouse::MouseInput;
 
 pub mod mouse;
 
+/// Pixels of touchpad scrolling counted as one mouse wheel step.
+const PIXELS_PER_LINE: f32 = 40.;
+
 #[derive(Copy, Clone, Debug, EnumCountMacro, EnumIter)]
 pub enum InputKey {
     MoveFront,
@@ -22,6 +28,14 @@ pub enum InputKey {
     ChunkGeneration,
     Mine,
     Fill,
+    Undo,
+    Redo,
+    /// Held to change the brush strength with the mouse wheel.
+    BrushStrength,
+    NextBrushShape,
+    NextBrushFalloff,
+    NextBrushMode,
+    PickMaterial,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -73,6 +87,12 @@ impl GameInput {
             WindowEvent::MouseInput { state, button, .. } => {
                 self.buttons_action(button, state == &ElementState::Pressed)
             }
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, y) => self.mouse.handle_wheel(*y),
+                MouseScrollDelta::PixelDelta(position) => {
+                    self.mouse.handle_wheel(position.y as f32 / PIXELS_PER_LINE)
+                }
+            },
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse.handle_move(position.x as f32, position.y as f32)
             }
@@ -147,6 +167,7 @@ impl GameInput {
         match key {
             MouseButton::Left => self.handle_key_action(InputKey::Mine, pressed),
             MouseButton::Right => self.handle_key_action(InputKey::Fill, pressed),
+            MouseButton::Middle => self.handle_key_action(InputKey::PickMaterial, pressed),
             _ => false,
         }
     }
@@ -162,6 +183,12 @@ impl GameInput {
             VirtualKeyCode::Q => self.handle_key_action(InputKey::CursorFree, pressed),
             VirtualKeyCode::LShift => self.handle_key_action(InputKey::FastMove, pressed),
             VirtualKeyCode::G => self.handle_key_action(InputKey::ChunkGeneration, pressed),
+            VirtualKeyCode::Z => self.handle_key_action(InputKey::Undo, pressed),
+            VirtualKeyCode::Y => self.handle_key_action(InputKey::Redo, pressed),
+            VirtualKeyCode::LAlt => self.handle_key_action(InputKey::BrushStrength, pressed),
+            VirtualKeyCode::B => self.handle_key_action(InputKey::NextBrushShape, pressed),
+            VirtualKeyCode::N => self.handle_key_action(InputKey::NextBrushFalloff, pressed),
+            VirtualKeyCode::M => self.handle_key_action(InputKey::NextBrushMode, pressed),
             _ => false,
         }
     }
@@ -5,6 +5,8 @@ pub struct MouseInput {
     y: f32,
     delta_x: f32,
     delta_y: f32,
+    /// Mouse wheel steps since the last update, up is positive.
+    wheel_delta: f32,
     pub prev_is_cursor_hidden: bool,
     pub is_mouse_hidden: bool,
 }
@@ -16,6 +18,7 @@ impl MouseInput {
             prev_is_cursor_hidden: !is_mouse_hidden,
             delta_x: 0.,
             delta_y: 0.,
+            wheel_delta: 0.,
             x: 0.,
             y: 0.,
         }
@@ -26,6 +29,7 @@ impl MouseInput {
     pub(super) fn post_update(&mut self) {
         self.delta_x *= 0.5;
         self.delta_y *= 0.5;
+        self.wheel_delta = 0.;
     }
 
     pub(super) fn handle_motion(&mut self, x: f32, y: f32) -> bool {
@@ -48,6 +52,11 @@ impl MouseInput {
         return true;
     }
 
+    pub(super) fn handle_wheel(&mut self, delta: f32) -> bool {
+        self.wheel_delta += delta;
+        true
+    }
+
     pub fn get_mouse_pos(&self) -> (f32, f32) {
         (self.x, self.y)
     }
@@ -55,4 +64,8 @@ impl MouseInput {
     pub fn get_delta(&self) -> (f32, f32) {
         (self.delta_x, self.delta_y)
     }
+
+    pub fn get_wheel_delta(&self) -> f32 {
+        self.wheel_delta
+    }
 }
@@ -1,4 +1,4 @@
-use std::iter;
+use std::{io, iter};
 use winit::{
     event::{DeviceEvent, WindowEvent},
     window::Window,
@@ -17,10 +17,10 @@ pub struct AppState {
 }
 
 impl AppState {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window) -> io::Result<Self> {
         let game_state = GameSate::new(window).await;
-        let world = World::new(window, &game_state);
-        Self { game_state, world }
+        let world = World::new(window, &game_state)?;
+        Ok(Self { game_state, world })
     }
 
     pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> {
@@ -49,6 +49,12 @@ impl AppState {
         self.game_state.post_update(window);
     }
 
+    pub fn save(&mut self) {
+        if let Err(err) = self.world.save() {
+            log::error!("failed to save world: {}", err);
+        }
+    }
+
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self
             .game_state
@@ -62,7 +62,13 @@ pub async fn run() {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let mut state = app_state::AppState::new(&window).await;
+    let mut state = match app_state::AppState::new(&window).await {
+        Ok(state) => state,
+        Err(err) => {
+            log::error!("failed to open world: {}", err);
+            return;
+        }
+    };
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
@@ -80,6 +86,9 @@ pub async fn run() {
         Event::RedrawEventsCleared => {
             window.request_redraw();
         }
+        Event::LoopDestroyed => {
+            state.save();
+        }
         _ => {}
     });
 }
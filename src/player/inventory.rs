@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use crate::world::voxel::{Voxel, VoxelId};
+
+/// Terrain carried by the player, as voxel density by material.
+///
+/// Digging adds the removed density and filling takes it back, so the density
+/// of the world plus the inventory stays the same.
+///
+/// Amounts can go negative: undoing a dig takes its density back even when it
+/// was already used elsewhere, so undo always works. The debt is repaid by
+/// digging that material again, and nothing is filled with it until then.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Inventory {
+    stock: BTreeMap<VoxelId, f32>,
+    /// Never runs out and keeps nothing, for edits that don't come from
+    /// playing, like replaying an edit log.
+    unlimited: bool,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn unlimited() -> Self {
+        Self {
+            stock: BTreeMap::new(),
+            unlimited: true,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.unlimited
+    }
+
+    /// Density of `material` carried, negative while in debt.
+    pub fn get_amount(&self, material: VoxelId) -> f32 {
+        if self.unlimited {
+            return f32::INFINITY;
+        }
+        self.stock.get(&material).copied().unwrap_or(0.)
+    }
+
+    pub fn get_total(&self) -> f32 {
+        self.stock.values().sum()
+    }
+
+    /// Materials carried with their amount, by id.
+    pub fn iter(&self) -> impl Iterator<Item = (VoxelId, f32)> + '_ {
+        self.stock
+            .iter()
+            .map(|(material, amount)| (*material, *amount))
+    }
+
+    /// Adds `amount` of `material`, negative amounts take it away.
+    pub fn add(&mut self, material: VoxelId, amount: f32) {
+        if self.unlimited || amount == 0. {
+            return;
+        }
+        *self.stock.entry(material).or_insert(0.) += amount;
+    }
+
+    /// Adds the density lost by a voxel changing from `before` to `after`, or
+    /// takes the density it gained. When the material changes, all of the
+    /// old material comes back and all of the new one is taken. Only the
+    /// density above [`EMPTY_VALUE`](crate::world::voxel::EMPTY_VALUE) counts,
+    /// values below it are empty.
+    pub fn exchange(&mut self, before: Voxel, after: Voxel) {
+        if before.material == after.material {
+            self.add(before.material, before.get_density() - after.get_density());
+        } else {
+            self.add(before.material, before.get_density());
+            self.add(after.material, -after.get_density());
+        }
+    }
+}
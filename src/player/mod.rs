@@ -1,20 +1,35 @@
+use std::f32::consts::PI;
+
 use crate::{
-    app_state::game_state::GameSate, utils::position::Position, vec::Vec3,
-    world::chunk::CHUNK_REAL_SIZE,
+    app_state::game_state::input::{GameInput, InputKey},
+    utils::position::Position,
+    vec::Vec3,
+    world::{brush::Brush, chunk::CHUNK_REAL_SIZE},
 };
-use wgpu::{BindGroupLayout, Device, Queue, RenderPass};
 
-use self::camera::{state::CameraState, Camera};
+use self::{camera::state::CameraState, inventory::Inventory};
+
+pub mod camera;
+pub mod inventory;
 
-mod camera;
+const SPEED: f32 = 10.0;
+const SENSITIVITY: f32 = 10.0;
+const FAST_MOVE_FACTOR: f32 = 5.;
+const MIN_Y_ANGLE: f32 = PI * 0.1;
 
 pub struct Player {
-    cam: Camera,
+    pub cam_state: CameraState,
+    pub inventory: Inventory,
+    pub brush: Brush,
+    /// Whether the material of the brush was picked. Until then filling uses
+    /// the material of the targeted voxel.
+    pub material_picked: bool,
+    screen_size: (f32, f32),
 }
 
 impl Player {
-    pub fn new(device: &Device, screen_size: (f32, f32)) -> Self {
-        let state = CameraState {
+    pub fn new(screen_size: (f32, f32)) -> Self {
+        let cam_state = CameraState {
             eye: (0., 0., 0.).into(),
             target: (0., 0., -1.0).into(),
             up: Vec3::new(0., 1., 0.),
@@ -24,39 +39,97 @@ impl Player {
             z_far: 1024.0,
         };
         Self {
-            cam: Camera::new(device, state, (screen_size.0, screen_size.1)),
+            cam_state,
+            inventory: Inventory::new(),
+            brush: Brush::default(),
+            material_picked: false,
+            screen_size,
         }
     }
 
     pub fn get_chunk_pos(&self) -> Position {
         Position::new(
-            (self.cam.state.eye.x / CHUNK_REAL_SIZE as f32) as i64,
-            (self.cam.state.eye.y / CHUNK_REAL_SIZE as f32) as i64,
-            (self.cam.state.eye.z / CHUNK_REAL_SIZE as f32) as i64,
+            (self.cam_state.eye.x / CHUNK_REAL_SIZE as f32).floor() as i64,
+            (self.cam_state.eye.y / CHUNK_REAL_SIZE as f32).floor() as i64,
+            (self.cam_state.eye.z / CHUNK_REAL_SIZE as f32).floor() as i64,
         )
     }
 
     pub fn get_pos(&self) -> Vec3<f32> {
-        self.cam.state.eye
+        self.cam_state.eye
+    }
+
+    pub fn set_pos(&mut self, pos: Vec3<f32>) {
+        self.translate_abs(pos - self.cam_state.eye);
     }
 
     pub fn get_look_dir(&self) -> Vec3<f32> {
-        (self.cam.state.target - self.get_pos()).normalize()
+        (self.cam_state.target - self.get_pos()).normalize()
     }
 
-    pub fn draw<'a>(self: &'a Self, render_pass: &mut RenderPass<'a>) {
-        render_pass.set_bind_group(0, self.cam.get_bind_group(), &[]);
+    pub fn translate_abs(&mut self, offset: Vec3<f32>) {
+        self.cam_state.eye += offset;
+        self.cam_state.target += offset;
     }
 
-    pub fn update_uniform(&mut self, queue: &Queue) {
-        self.cam.update_uniform(queue);
+    pub fn translate(&mut self, offset: Vec3<f32>) {
+        let front = self.cam_state.target - self.cam_state.eye;
+        let right = front.cross(self.cam_state.up).normalize();
+        let top = right.cross(front);
+
+        let abs_offset = right * offset.x + top * offset.y + front * offset.z;
+
+        self.translate_abs(abs_offset);
     }
 
-    pub fn update(&mut self, game_state: &mut GameSate) {
-        self.cam.update(game_state);
+    pub fn rotate(&mut self, x: f32, y: f32) {
+        let mut y = y;
+
+        let front = (self.cam_state.target - self.cam_state.eye).normalize();
+        let front = front.rotate(Vec3::unit_y(), -x);
+
+        let right = front.cross(self.cam_state.up).normalize();
+
+        let y_angle = front.angle(Vec3::unit_y());
+        if y > y_angle - MIN_Y_ANGLE {
+            y = y_angle - MIN_Y_ANGLE;
+        } else if y < y_angle - PI + MIN_Y_ANGLE {
+            y = y_angle - PI + MIN_Y_ANGLE;
+        }
+        let front = front.rotate(right, y);
+
+        self.cam_state.target = front + self.cam_state.eye;
     }
 
-    pub fn get_bind_group_layout(&self) -> &BindGroupLayout {
-        self.cam.get_bind_group_layout()
+    pub fn update(&mut self, input: &GameInput, dt: f32) {
+        let mut move_offset = dt * SPEED;
+        if input.is_pressed(InputKey::FastMove) {
+            move_offset *= FAST_MOVE_FACTOR;
+        }
+
+        if input.is_pressed(InputKey::MoveFront) {
+            self.translate(Vec3::unit_z() * move_offset);
+        }
+        if input.is_pressed(InputKey::MoveLeft) {
+            self.translate(-Vec3::unit_x() * move_offset);
+        }
+        if input.is_pressed(InputKey::MoveBack) {
+            self.translate(-Vec3::unit_z() * move_offset);
+        }
+        if input.is_pressed(InputKey::MoveRight) {
+            self.translate(Vec3::unit_x() * move_offset);
+        }
+        if input.is_pressed(InputKey::MoveUp) {
+            self.translate_abs(Vec3::unit_y() * move_offset);
+        }
+        if input.is_pressed(InputKey::MoveDown) {
+            self.translate_abs(-Vec3::unit_y() * move_offset);
+        }
+
+        let (dx, dy) = input.mouse.get_delta();
+        self.rotate(
+            dx / self.screen_size.0 * SENSITIVITY,
+            -dy / self.screen_size.0 * SENSITIVITY,
+        );
     }
 }
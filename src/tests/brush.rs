@@ -0,0 +1,246 @@
+use crate::{
+    player::inventory::Inventory,
+    utils::position::Position,
+    world::{
+        brush::{Brush, BrushMode, BrushShape, Falloff},
+        voxel::{
+            material::{MaterialRegistry, BEDROCK, GRASS, ROCK},
+            EditOutcome, Voxel, EMPTY_VALUE,
+        },
+    },
+};
+
+fn brush(shape: BrushShape, mode: BrushMode) -> Brush {
+    Brush {
+        shape,
+        falloff: Falloff::Constant,
+        mode,
+        radius: 4.,
+        height: 6.,
+        strength: 0.5,
+        material: GRASS,
+    }
+}
+
+fn apply(brush: &Brush, voxel: &mut Voxel, offset: Position, average: f32) -> EditOutcome {
+    let mut inventory = Inventory::unlimited();
+    brush.apply(
+        voxel,
+        offset,
+        average,
+        &MaterialRegistry::default(),
+        &mut inventory,
+    )
+}
+
+fn assert_near(value: f32, expected: f32) {
+    assert!((value - expected).abs() < 1e-6, "{} != {}", value, expected);
+}
+
+#[test]
+fn shapes_cover_their_volume() {
+    let inside = |shape: BrushShape, x: i64, y: i64, z: i64| {
+        brush(shape, BrushMode::Add).get_weight(Position::new(x, y, z)) > 0.
+    };
+
+    assert!(inside(BrushShape::Sphere, 0, 3, 0));
+    assert!(!inside(BrushShape::Sphere, 3, 3, 0));
+    assert!(inside(BrushShape::Cube, 3, 3, 3));
+    assert!(!inside(BrushShape::Cube, 4, 0, 0));
+    assert!(inside(BrushShape::Cylinder, 2, 5, 2));
+    assert!(!inside(BrushShape::Cylinder, 0, 6, 0));
+    assert!(!inside(BrushShape::Cylinder, 3, 0, 3));
+    assert!(inside(BrushShape::Capsule, 0, 9, 0));
+    assert!(!inside(BrushShape::Capsule, 3, 9, 0));
+
+    for shape in [
+        BrushShape::Sphere,
+        BrushShape::Cube,
+        BrushShape::Cylinder,
+        BrushShape::Capsule,
+    ] {
+        let brush = brush(shape, BrushMode::Add);
+        let extent = brush.get_extent();
+        assert_eq!(brush.get_weight(extent + Position::new(0, 1, 0)), 0.);
+        assert_eq!(brush.get_weight(Position::new(0, extent.y + 1, 0)), 0.);
+    }
+}
+
+#[test]
+fn falloffs_go_from_center_to_edge() {
+    for falloff in [
+        Falloff::Constant,
+        Falloff::Linear,
+        Falloff::Smooth,
+        Falloff::Sharp,
+    ] {
+        assert_eq!(falloff.get_weight(0.), 1.);
+        assert_eq!(falloff.get_weight(1.), 0.);
+    }
+    assert_eq!(Falloff::Constant.get_weight(0.9), 1.);
+    assert_eq!(Falloff::Linear.get_weight(0.5), 0.5);
+    assert_eq!(Falloff::Smooth.get_weight(0.5), 0.5);
+    assert_eq!(Falloff::Sharp.get_weight(0.5), 0.25);
+}
+
+#[test]
+fn smooth_moves_towards_neighbours() {
+    let smooth = brush(BrushShape::Sphere, BrushMode::Smooth);
+    let mut spike = Voxel {
+        value: 1.,
+        material: ROCK,
+    };
+    assert_eq!(
+        apply(&smooth, &mut spike, Position::new(0, 0, 0), 0.2),
+        EditOutcome::Changed
+    );
+    assert_near(spike.value, 0.5);
+
+    let mut hole = Voxel {
+        value: -0.1,
+        material: ROCK,
+    };
+    apply(&smooth, &mut hole, Position::new(0, 0, 0), 0.2);
+    assert_near(hole.value, 0.2);
+}
+
+#[test]
+fn flatten_moves_towards_center_plane() {
+    let flatten = brush(BrushShape::Cube, BrushMode::Flatten);
+    let mut above = Voxel {
+        value: 0.3,
+        material: ROCK,
+    };
+    apply(&flatten, &mut above, Position::new(0, 2, 0), 0.);
+    assert_near(above.value, -0.1);
+
+    let mut below = Voxel {
+        value: -0.1,
+        material: ROCK,
+    };
+    apply(&flatten, &mut below, Position::new(0, -2, 0), 0.);
+    assert_near(below.value, 0.4);
+
+    let mut bedrock = Voxel {
+        value: 0.3,
+        material: BEDROCK,
+    };
+    assert_eq!(
+        apply(&flatten, &mut bedrock, Position::new(0, 2, 0), 0.),
+        EditOutcome::Resisted
+    );
+    assert_eq!(bedrock.value, 0.3);
+}
+
+#[test]
+fn edits_charge_only_density_above_empty() {
+    let materials = MaterialRegistry::default();
+    let fill = Brush {
+        material: ROCK,
+        ..brush(BrushShape::Sphere, BrushMode::Add)
+    };
+    let mut inventory = Inventory::new();
+    inventory.add(ROCK, 2.);
+
+    // raised to empty for free, then filled by the strength
+    let mut deep = Voxel {
+        value: -1.,
+        material: GRASS,
+    };
+    fill.apply(
+        &mut deep,
+        Position::new(0, 0, 0),
+        0.,
+        &materials,
+        &mut inventory,
+    );
+    assert_near(deep.value, 0.4);
+    assert_near(inventory.get_amount(ROCK), 1.5);
+
+    // lowering never raises a voxel below empty
+    let smooth = brush(BrushShape::Sphere, BrushMode::Smooth);
+    let mut deep = Voxel {
+        value: -0.5,
+        material: ROCK,
+    };
+    assert_eq!(
+        smooth.apply(
+            &mut deep,
+            Position::new(0, 0, 0),
+            -1.,
+            &materials,
+            &mut inventory
+        ),
+        EditOutcome::Unchanged
+    );
+    assert_eq!(deep.value, -0.5);
+    assert_near(inventory.get_amount(ROCK), 1.5);
+
+    // digging past empty only gives what was above it
+    let mut shallow = Voxel {
+        value: 0.1,
+        material: ROCK,
+    };
+    let dig = brush(BrushShape::Sphere, BrushMode::Subtract);
+    dig.apply(
+        &mut shallow,
+        Position::new(0, 0, 0),
+        0.,
+        &materials,
+        &mut inventory,
+    );
+    assert_eq!(shallow.value, EMPTY_VALUE);
+    assert_near(inventory.get_amount(ROCK), 1.7);
+}
+
+#[test]
+fn paint_changes_only_material_of_solid_voxels() {
+    let materials = MaterialRegistry::default();
+    let paint = brush(BrushShape::Sphere, BrushMode::Paint);
+    let mut solid = Voxel {
+        value: 0.4,
+        material: ROCK,
+    };
+
+    // the new material has to be carried
+    let mut inventory = Inventory::new();
+    inventory.add(GRASS, 0.25);
+    assert_eq!(
+        paint.apply(
+            &mut solid,
+            Position::new(1, 0, 0),
+            0.,
+            &materials,
+            &mut inventory
+        ),
+        EditOutcome::Unchanged
+    );
+    assert_eq!(solid.material, ROCK);
+
+    // and is traded for the replaced one
+    inventory.add(GRASS, 0.75);
+    assert_eq!(
+        paint.apply(
+            &mut solid,
+            Position::new(1, 0, 0),
+            0.,
+            &materials,
+            &mut inventory
+        ),
+        EditOutcome::Changed
+    );
+    assert_eq!(solid.material, GRASS);
+    assert_eq!(solid.value, 0.4);
+    assert_near(inventory.get_amount(GRASS), 0.5);
+    assert_near(inventory.get_amount(ROCK), 0.5);
+
+    let mut empty = Voxel {
+        value: -0.05,
+        material: ROCK,
+    };
+    assert_eq!(
+        apply(&paint, &mut empty, Position::new(1, 0, 0), 0.),
+        EditOutcome::Unchanged
+    );
+    assert_eq!(empty.material, ROCK);
+}
@@ -0,0 +1,122 @@
+use std::{fs, io};
+
+use chrono::{TimeZone, Utc};
+
+use crate::{
+    player::inventory::Inventory,
+    tests::TempDir,
+    utils::position::Position,
+    world::{
+        brush::{Brush, BrushMode, BrushShape, Falloff},
+        edit_log::{Edit, EditLog, EditRecord},
+        voxel::{
+            material::{GRASS, ROCK},
+            Voxel,
+        },
+    },
+};
+
+fn temp_log(name: &str) -> TempDir {
+    TempDir::new(&format!("edit-log-{}", name))
+}
+
+fn record(mode: BrushMode, x: i64, inventory: Inventory) -> EditRecord {
+    let brush = Brush {
+        material: GRASS,
+        ..Brush::sphere(mode, 6., 0.0125)
+    };
+    brush_record(brush, x, inventory)
+}
+
+fn brush_record(brush: Brush, x: i64, inventory: Inventory) -> EditRecord {
+    EditRecord {
+        time: Utc.ymd(2026, 10, 18).and_hms_micro(12, 30, 5, 250_001),
+        edit: Edit::Brush {
+            center: Position::new(x, -3, 7),
+            brush,
+            inventory,
+        },
+    }
+}
+
+#[test]
+fn records_round_trip() {
+    let dir = temp_log("round-trip");
+    let path = dir.join("edits.log");
+    let mut stock = Inventory::new();
+    stock.add(GRASS, 2.5);
+    stock.add(ROCK, 0.75);
+    let cylinder = Brush {
+        shape: BrushShape::Cylinder,
+        falloff: Falloff::Smooth,
+        height: 2.5,
+        ..Brush::sphere(BrushMode::Flatten, 6., 0.0125)
+    };
+    let voxels = vec![
+        (
+            Position::new(-1, 40, 3),
+            Voxel {
+                value: 0.3125,
+                material: ROCK,
+            },
+        ),
+        (
+            Position::new(0, 40, 3),
+            Voxel {
+                value: -1.,
+                material: GRASS,
+            },
+        ),
+    ];
+
+    let records = vec![
+        record(BrushMode::Subtract, 1, Inventory::unlimited()),
+        record(BrushMode::Add, -20, stock),
+        brush_record(cylinder, 4, Inventory::new()),
+        EditRecord {
+            time: Utc.ymd(2026, 10, 18).and_hms(12, 31, 0),
+            edit: Edit::Voxels(voxels),
+        },
+    ];
+
+    let mut log = EditLog::open(&path).unwrap();
+    for record in records.iter() {
+        log.append(record).unwrap();
+    }
+    log.flush().unwrap();
+
+    assert_eq!(EditLog::read(&path).unwrap(), records);
+}
+
+#[test]
+fn reopened_log_appends() {
+    let dir = temp_log("append");
+    let path = dir.join("edits.log");
+
+    for x in 0..3 {
+        let mut log = EditLog::open(&path).unwrap();
+        log.append(&record(BrushMode::Subtract, x, Inventory::new()))
+            .unwrap();
+    }
+
+    let centers: Vec<i64> = EditLog::read(&path)
+        .unwrap()
+        .iter()
+        .map(|record| match &record.edit {
+            Edit::Brush { center, .. } => center.x,
+            Edit::Voxels(_) => panic!("expected a brush edit"),
+        })
+        .collect();
+    assert_eq!(centers, vec![0, 1, 2]);
+}
+
+#[test]
+fn malformed_log_is_rejected() {
+    let dir = temp_log("malformed");
+    let path = dir.join("edits.log");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&path, "2026-10-18T12:30:05Z smash 1 2 3 6 0.5 -\n").unwrap();
+
+    let err = EditLog::read(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
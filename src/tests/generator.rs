@@ -0,0 +1,440 @@
+use std::thread;
+
+use crate::{
+    utils::position::Position,
+    world::{
+        chunk::{Chunk, CHUNK_VOXELS_SIZE, CHUNK_VOXELS_VOLUME},
+        generator::{
+            biome::Biome,
+            graph::{GraphGenerator, DEFAULT_TERRAIN_GRAPH},
+            Generator, GeneratorParams, TerrainGenerator,
+        },
+        voxel::{
+            material::{GRASS, ROCK},
+            Voxel, VoxelId,
+        },
+    },
+};
+
+const POSITIONS: [Position; 4] = [
+    Position { x: 0, y: 0, z: 0 },
+    Position { x: -3, y: 0, z: 7 },
+    Position {
+        x: 12,
+        y: -1,
+        z: -20,
+    },
+    Position { x: -1, y: 1, z: -1 },
+];
+
+fn chunk_bits(generator: &dyn TerrainGenerator, pos: Position) -> Vec<u32> {
+    let mut chunk = Chunk::new(pos);
+    chunk.generate_voxels(generator);
+
+    let mut bits = Vec::with_capacity(CHUNK_VOXELS_VOLUME * 2);
+    for voxel in chunk.get_voxels() {
+        bits.push(voxel.value.to_bits());
+        bits.push(voxel.material);
+    }
+    bits
+}
+
+#[test]
+fn same_seed_same_voxels() {
+    for pos in POSITIONS {
+        assert_eq!(
+            chunk_bits(&Generator::with_seed(42), pos),
+            chunk_bits(&Generator::with_seed(42), pos)
+        );
+    }
+}
+
+#[test]
+fn same_seed_same_voxels_across_threads() {
+    let expected: Vec<Vec<u32>> = POSITIONS
+        .iter()
+        .map(|pos| chunk_bits(&Generator::with_seed(7), *pos))
+        .collect();
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                let generator = Generator::with_seed(7);
+                POSITIONS
+                    .iter()
+                    .rev()
+                    .map(|pos| chunk_bits(&generator, *pos))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    for h in handles {
+        let mut result = h.join().unwrap();
+        result.reverse();
+        assert_eq!(expected, result);
+    }
+}
+
+#[test]
+fn different_seed_different_voxels() {
+    let pos = Position::new(0, 0, 0);
+    assert_ne!(
+        chunk_bits(&Generator::with_seed(1), pos),
+        chunk_bits(&Generator::with_seed(2), pos)
+    );
+}
+
+#[test]
+fn with_seed_uses_default_params() {
+    let generator = Generator::with_seed(99);
+    assert_eq!(generator.get_params(), GeneratorParams::with_seed(99));
+    assert_eq!(generator.get_params().seed, 99);
+}
+
+#[test]
+fn default_graph_matches_generator() {
+    let graph = GraphGenerator::from_ron(DEFAULT_TERRAIN_GRAPH).unwrap();
+    for pos in POSITIONS {
+        assert_eq!(chunk_bits(&graph, pos), chunk_bits(&Generator::new(), pos));
+    }
+}
+
+fn eval_graph(value: &str) -> f32 {
+    let graph = format!(
+        "TerrainGraph(seed: 1, value: {}, material: (default: \"sand\"))",
+        value
+    );
+    let generator = GraphGenerator::from_ron(&graph).unwrap();
+    let mut chunk = Chunk::new(Position::new(0, 0, 0));
+    chunk.generate_voxels(&generator);
+    chunk.get_voxel(Position::new(2, 3, 4)).unwrap().value
+}
+
+#[test]
+fn graph_nodes() {
+    assert_eq!(eval_graph("Add([X, Y, Z])"), 9.);
+    assert_eq!(
+        eval_graph("Min(Mul([X, Constant(4.0)]), Div(Z, Constant(2.0)))"),
+        2.
+    );
+    assert_eq!(
+        eval_graph("Clamp(input: Neg(Y), min: -0.1, max: 1.0)"),
+        -0.1
+    );
+    assert_eq!(eval_graph("Blend(a: X, b: Z, t: Constant(0.25))"), 2.5);
+    assert_eq!(
+        eval_graph("Warp(input: Y, offset: (Constant(0.0), X, Constant(0.0)))"),
+        5.
+    );
+    assert_eq!(
+        eval_graph("Fbm(input: [X, Y], octaves: 1, lacunarity: 2.0, persistence: 0.5)"),
+        eval_graph("Simplex([X, Y])")
+    );
+}
+
+#[test]
+fn graph_refs_and_materials() {
+    let graph = r#"TerrainGraph(
+        seed: 1,
+        nodes: {
+            "height": Sub(Constant(4.0), Y),
+            "solid": Ref("height"),
+        },
+        value: Ref("solid"),
+        material: (
+            default: "rock",
+            rules: [(input: Ref("height"), below: Constant(2.0), material: "grass")],
+        ),
+    )"#;
+    let generator = GraphGenerator::from_ron(graph).unwrap();
+    let mut chunk = Chunk::new(Position::new(0, 0, 0));
+    chunk.generate_voxels(&generator);
+
+    let low = chunk.get_voxel(Position::new(0, 1, 0)).unwrap();
+    assert_eq!(low.value, 3.);
+    assert_eq!(low.material, ROCK);
+    assert_eq!(
+        chunk.get_voxel(Position::new(0, 3, 0)).unwrap().material,
+        GRASS
+    );
+}
+
+#[test]
+fn invalid_graphs_are_rejected() {
+    let graph = |nodes: &str, value: &str| {
+        GraphGenerator::from_ron(&format!(
+            "TerrainGraph(seed: 0, nodes: {{{}}}, value: {}, material: (default: \"rock\"))",
+            nodes, value
+        ))
+    };
+
+    assert!(graph("", "Ref(\"missing\")").is_err());
+    assert!(graph("\"a\": Ref(\"b\"), \"b\": Ref(\"a\")", "X").is_err());
+    assert!(graph("", "Simplex([X])").is_err());
+    assert!(graph("", "Add([])").is_err());
+    assert!(graph("", "Clamp(input: X, min: 1.0, max: 0.0)").is_err());
+    assert!(graph("", "Sqrt(X)").is_err());
+    assert!(graph("\"a\": X", "Y").is_ok());
+    assert!(GraphGenerator::from_ron(
+        "TerrainGraph(seed: 0, value: X, material: (default: \"unobtainium\"))"
+    )
+    .is_err());
+}
+
+fn biome_generator(seed: u32) -> Generator {
+    Generator::from_params(GeneratorParams {
+        biomes: true,
+        ..GeneratorParams::with_seed(seed)
+    })
+}
+
+#[test]
+fn no_biomes_without_biome_params() {
+    assert_eq!(Generator::new().get_biome(Position::new(0, 0, 0)), None);
+}
+
+#[test]
+fn every_biome_appears() {
+    let generator = biome_generator(7);
+    let mut found = vec![];
+
+    for x in -40..40 {
+        for z in -40..40 {
+            let biome = generator
+                .get_biome(Position::new(x * 150, 0, z * 150))
+                .unwrap();
+            if !found.contains(&biome) {
+                found.push(biome);
+            }
+        }
+    }
+
+    for biome in [
+        Biome::Desert,
+        Biome::Tundra,
+        Biome::Forest,
+        Biome::Mountains,
+    ] {
+        assert!(found.contains(&biome), "{:?} not found", biome);
+    }
+}
+
+/// Neighbouring columns along the x axis whose strongest biomes differ.
+fn find_biome_border(generator: &Generator) -> (i64, i64) {
+    let biome_at = |x: i64| generator.get_biome(Position::new(x, 0, 0)).unwrap();
+
+    let mut from = 0;
+    let mut to = (1..200)
+        .map(|i| i * 100)
+        .find(|x| biome_at(*x) != biome_at(0))
+        .unwrap();
+    while to - from > 1 {
+        let middle = (from + to) / 2;
+        if biome_at(middle) == biome_at(from) {
+            from = middle;
+        } else {
+            to = middle;
+        }
+    }
+    (from, to)
+}
+
+/// Height and voxel of the highest solid voxel of the column at `x`, `0`.
+fn find_surface(generator: &Generator, x: i64) -> (i64, Voxel) {
+    let chunk_x = x.div_euclid(16);
+    (-6..6)
+        .rev()
+        .flat_map(|chunk_y| {
+            let mut chunk = Chunk::new(Position::new(chunk_x, chunk_y, 0));
+            chunk.generate_voxels(generator);
+            (0..16)
+                .rev()
+                .map(move |y| {
+                    (
+                        chunk_y * 16 + y,
+                        chunk
+                            .get_voxel(Position::new(x - chunk_x * 16, y, 0))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .find(|(_, voxel)| voxel.value >= 0.)
+        .unwrap()
+}
+
+#[test]
+fn biome_borders_are_blended() {
+    let generator = biome_generator(7);
+    let (from, to) = find_biome_border(&generator);
+
+    // the terrain height around the border changes by a voxel or two at most
+    // per column
+    let heights: Vec<i64> = (from - 4..to + 4)
+        .map(|x| find_surface(&generator, x).0)
+        .collect();
+    for pair in heights.windows(2) {
+        assert!((pair[0] - pair[1]).abs() <= 2, "{:?}", heights);
+    }
+}
+
+#[test]
+fn biome_materials_are_dithered_across_borders() {
+    let generator = biome_generator(7);
+    let (from, to) = find_biome_border(&generator);
+    let near = generator.get_biome(Position::new(from, 0, 0)).unwrap();
+    let far = generator.get_biome(Position::new(to, 0, 0)).unwrap();
+    let near_material = near.get_params().surface_material;
+    let far_material = far.get_params().surface_material;
+    assert_ne!(near_material, far_material);
+
+    // on both sides of the border, some columns take the materials of the
+    // other biome
+    let materials = |range: std::ops::Range<i64>| -> Vec<VoxelId> {
+        range
+            .map(|x| find_surface(&generator, x).1.material)
+            .collect()
+    };
+    let before = materials(from - 16..from + 1);
+    let after = materials(to..to + 16);
+    assert!(before.contains(&near_material), "{:?}", before);
+    assert!(before.contains(&far_material), "{:?}", before);
+    assert!(after.contains(&near_material), "{:?}", after);
+    assert!(after.contains(&far_material), "{:?}", after);
+}
+
+fn cave_generator(seed: u32, caves: bool) -> Generator {
+    Generator::from_params(GeneratorParams {
+        caves,
+        ..GeneratorParams::with_seed(seed)
+    })
+}
+
+#[test]
+fn caves_carve_underground() {
+    let plain = cave_generator(3, false);
+    let caves = cave_generator(3, true);
+    let mut carved = 0;
+
+    for x in -4..4 {
+        for y in -6..-2 {
+            for z in -4..4 {
+                let pos = Position::new(x, y, z);
+                let mut plain_chunk = Chunk::new(pos);
+                plain_chunk.generate_voxels(&plain);
+                let mut cave_chunk = Chunk::new(pos);
+                cave_chunk.generate_voxels(&caves);
+
+                for (a, b) in plain_chunk.get_voxels().iter().zip(cave_chunk.get_voxels()) {
+                    // caves only ever remove terrain
+                    assert!(b.value <= a.value);
+                    if a.value >= 0. && b.value < 0. {
+                        carved += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(carved > 0);
+}
+
+#[test]
+fn caves_match_across_chunk_borders() {
+    let generator = cave_generator(3, true);
+    let size = CHUNK_VOXELS_SIZE;
+
+    for x in -3..3 {
+        for y in -5..-3 {
+            let mut west = Chunk::new(Position::new(x, y, 0));
+            west.generate_voxels(&generator);
+            let mut east = Chunk::new(Position::new(x + 1, y, 0));
+            east.generate_voxels(&generator);
+
+            for y in 0..size {
+                for z in 0..size {
+                    let west = west.get_voxels()[size - 1 + y * size + z * size * size];
+                    let east = east.get_voxels()[y * size + z * size * size];
+                    assert_eq!(west.value.to_bits(), east.value.to_bits());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn caves_do_not_depend_on_generation_order() {
+    let expected: Vec<Vec<u32>> = POSITIONS
+        .iter()
+        .map(|pos| chunk_bits(&cave_generator(5, true), *pos))
+        .collect();
+
+    let generator = cave_generator(5, true);
+    let mut result: Vec<Vec<u32>> = POSITIONS
+        .iter()
+        .rev()
+        .map(|pos| chunk_bits(&generator, *pos))
+        .collect();
+    result.reverse();
+
+    assert_eq!(expected, result);
+}
+
+/// Voxels generated with seed 42 by the default params, as `(chunk, voxel in
+/// chunk, value, material)`. Any change to the terrain shows up here.
+const GOLDEN_VOXELS: [(Position, Position, f32, VoxelId); 6] = [
+    (
+        Position { x: 1, y: -3, z: -2 },
+        Position { x: 6, y: 5, z: 11 },
+        0.033283025,
+        ROCK,
+    ),
+    (
+        Position { x: 1, y: -1, z: -2 },
+        Position { x: 6, y: 5, z: 11 },
+        0.001283026,
+        GRASS,
+    ),
+    (
+        Position { x: 1, y: 0, z: -2 },
+        Position { x: 6, y: 10, z: 11 },
+        -0.019716974,
+        GRASS,
+    ),
+    (
+        Position { x: -5, y: -2, z: 3 },
+        Position { x: 2, y: 14, z: 9 },
+        0.009644007,
+        ROCK,
+    ),
+    (
+        Position { x: 3, y: -1, z: 4 },
+        Position { x: 12, y: 3, z: 0 },
+        0.0038746332,
+        ROCK,
+    ),
+    (
+        Position { x: 0, y: 0, z: 0 },
+        Position { x: 3, y: 5, z: 7 },
+        -0.017541094,
+        GRASS,
+    ),
+];
+
+#[test]
+fn seeded_terrain_matches_golden_voxels() {
+    let generator = Generator::with_seed(42);
+
+    for (chunk_pos, in_chunk_pos, value, material) in GOLDEN_VOXELS {
+        let mut chunk = Chunk::new(chunk_pos);
+        chunk.generate_voxels(&generator);
+        let voxel = chunk.get_voxel(in_chunk_pos).unwrap();
+        assert_eq!(
+            (voxel.value, voxel.material),
+            (value, material),
+            "{:?}",
+            chunk_pos
+        );
+    }
+}
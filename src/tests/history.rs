@@ -0,0 +1,76 @@
+use crate::{
+    utils::position::Position,
+    world::{history::EditHistory, voxel::Voxel},
+};
+
+fn voxel(value: f32) -> Voxel {
+    Voxel { value, material: 0 }
+}
+
+fn values(voxels: Vec<(Position, Voxel)>) -> Vec<f32> {
+    voxels.into_iter().map(|(_, voxel)| voxel.value).collect()
+}
+
+#[test]
+fn stroke_keeps_first_before_and_last_after() {
+    let mut history = EditHistory::default();
+    let pos = Position::new(1, 2, 3);
+
+    history.begin_stroke();
+    history.record(pos, voxel(1.), voxel(0.5));
+    history.record(pos, voxel(0.5), voxel(0.));
+    history.end_stroke();
+
+    assert_eq!(history.get_undo_count(), 1);
+    assert_eq!(values(history.undo().unwrap()), vec![1.]);
+    assert_eq!(values(history.redo().unwrap()), vec![0.]);
+}
+
+#[test]
+fn edits_outside_stroke_are_ignored() {
+    let mut history = EditHistory::default();
+    history.record(Position::new(0, 0, 0), voxel(1.), voxel(0.));
+    history.end_stroke();
+
+    assert!(history.undo().is_none());
+}
+
+#[test]
+fn new_stroke_clears_redo() {
+    let mut history = EditHistory::default();
+    for i in 0..2 {
+        history.begin_stroke();
+        history.record(Position::new(i, 0, 0), voxel(1.), voxel(0.));
+        history.end_stroke();
+    }
+
+    history.undo().unwrap();
+    assert_eq!(history.get_redo_count(), 1);
+
+    history.begin_stroke();
+    history.record(Position::new(5, 0, 0), voxel(1.), voxel(0.));
+    history.end_stroke();
+
+    assert_eq!(history.get_redo_count(), 0);
+    assert_eq!(history.get_undo_count(), 2);
+    assert!(history.redo().is_none());
+}
+
+#[test]
+fn memory_cap_drops_oldest_strokes() {
+    let mut history = EditHistory::default();
+    for i in 0..4 {
+        history.begin_stroke();
+        history.record(Position::new(i, 0, 0), voxel(i as f32), voxel(-1.));
+        history.end_stroke();
+    }
+    let stroke_size = history.get_memory_used() / 4;
+
+    history.set_memory_limit(stroke_size * 2);
+    assert_eq!(history.get_undo_count(), 2);
+    assert!(history.get_memory_used() <= history.get_memory_limit());
+
+    assert_eq!(values(history.undo().unwrap()), vec![3.]);
+    assert_eq!(values(history.undo().unwrap()), vec![2.]);
+    assert!(history.undo().is_none());
+}
@@ -0,0 +1,103 @@
+use crate::{
+    player::inventory::Inventory,
+    tests::{load_around, surface_below, temp_core, temp_core_with, world_density},
+    utils::position::Position,
+    world::{
+        brush::{Brush, BrushMode},
+        core::WorldCore,
+        generator::flat::FlatGenerator,
+        voxel::{
+            material::{GRASS, ROCK, SAND},
+            Voxel,
+        },
+    },
+};
+
+#[test]
+fn dig_and_fill_conserve_density() {
+    let (mut core, _dir) = temp_core("inventory-conserve");
+    load_around(&mut core, Position::new(0, -1, 0));
+    let center = surface_below(&core, 8, 8);
+    let total = world_density(&core, center, 8);
+    let conserved = |core: &WorldCore| {
+        let now = world_density(core, center, 8) + core.player.inventory.get_total() as f64;
+        (now - total).abs() < 1e-3
+    };
+
+    let dig = Brush::sphere(BrushMode::Subtract, 5., 0.8);
+    let report = core.apply_brush(&dig, center);
+    assert!(report.changed > 0);
+    assert!(core.player.inventory.get_amount(GRASS) > 0.);
+    assert!(core.player.inventory.get_amount(ROCK) > 0.);
+    assert!(conserved(&core));
+
+    let fill = Brush {
+        material: ROCK,
+        ..Brush::sphere(BrushMode::Add, 4., 0.5)
+    };
+    core.apply_brush(&fill, center + Position::new(0, 2, 0));
+    assert!(conserved(&core));
+}
+
+#[test]
+fn fill_stops_when_stock_runs_out() {
+    // nothing but air of rock around the center
+    let generator = FlatGenerator::new(-32., ROCK);
+    let (mut core, _dir) = temp_core_with("inventory-stock", Box::new(generator));
+    let center = Position::new(8, 8, 8);
+    load_around(&mut core, Position::new(0, 0, 0));
+    core.player.inventory.add(SAND, 1.5);
+    let total = world_density(&core, center, 7) + core.player.inventory.get_total() as f64;
+
+    let fill = Brush {
+        material: SAND,
+        ..Brush::sphere(BrushMode::Add, 6., 1.)
+    };
+    let report = core.apply_brush(&fill, center);
+
+    assert!(report.changed > 0);
+    assert!(core.player.inventory.get_amount(SAND).abs() < 1e-6);
+    let now = world_density(&core, center, 7) + core.player.inventory.get_total() as f64;
+    assert!((now - total).abs() < 1e-4);
+
+    // nothing left to fill with
+    assert_eq!(core.apply_brush(&fill, center).changed, 0);
+    // other materials were never carried
+    let grass = Brush {
+        material: GRASS,
+        ..fill
+    };
+    assert_eq!(core.apply_brush(&grass, center).changed, 0);
+}
+
+#[test]
+fn exchange_credits_removed_and_debits_added_material() {
+    let mut inventory = Inventory::new();
+    let rock = Voxel {
+        value: 0.5,
+        material: ROCK,
+    };
+    let sand = Voxel {
+        value: 0.75,
+        material: SAND,
+    };
+    let air = Voxel {
+        value: -0.1,
+        material: ROCK,
+    };
+
+    inventory.exchange(rock, air);
+    inventory.exchange(air, sand);
+    assert!((inventory.get_amount(ROCK) - 0.6).abs() < 1e-6);
+    assert!((inventory.get_amount(SAND) + 0.85).abs() < 1e-6);
+
+    // a new material trades all of the density of the old one
+    inventory.exchange(sand, rock);
+    assert!((inventory.get_amount(ROCK)).abs() < 1e-6);
+    assert!(inventory.get_amount(SAND).abs() < 1e-6);
+
+    let mut unlimited = Inventory::unlimited();
+    unlimited.exchange(air, sand);
+    assert_eq!(unlimited.get_amount(SAND), f32::INFINITY);
+    assert_eq!(unlimited.iter().count(), 0);
+}
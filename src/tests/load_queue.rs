@@ -0,0 +1,148 @@
+use crate::{
+    utils::position::Position,
+    vec::Vec3,
+    world::{
+        chunk::CHUNK_REAL_SIZE,
+        core::load_queue::{LoadFocus, LoadQueue, LoadRange},
+    },
+};
+
+/// Center of the chunk at the origin.
+fn eye() -> Vec3<f32> {
+    Vec3::new(1., 1., 1.).mul_scalar(CHUNK_REAL_SIZE as f32 / 2.)
+}
+
+fn looking(look_dir: Vec3<f32>) -> LoadFocus {
+    LoadFocus {
+        eye: eye(),
+        look_dir,
+        ..LoadFocus::default()
+    }
+}
+
+fn drain(queue: &mut LoadQueue) -> Vec<Position> {
+    let mut positions = vec![];
+    while let Some(pos) = queue.pop(|_| true) {
+        positions.push(pos);
+    }
+    positions
+}
+
+#[test]
+fn nearest_chunks_in_view_come_first() {
+    let mut queue = LoadQueue::new();
+    let focus = looking(Vec3::new(1., 0., 0.));
+    queue.update(Position::new(0, 0, 0), LoadRange::new(3, 3), focus, 2.);
+    assert_eq!(queue.len(), 179);
+
+    let order = drain(&mut queue);
+    assert_eq!(order[0], Position::new(0, 0, 0));
+    assert_eq!(order[1], Position::new(1, 0, 0));
+
+    let priorities: Vec<f32> = order
+        .iter()
+        .map(|pos| LoadQueue::get_priority(*pos, &focus))
+        .collect();
+    assert!(priorities.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    let index = |pos: Position| order.iter().position(|p| *p == pos).unwrap();
+    assert!(index(Position::new(1, 0, 0)) < index(Position::new(-1, 0, 0)));
+    assert!(index(Position::new(2, 0, 0)) < index(Position::new(0, 2, 0)));
+    assert!(index(Position::new(1, 1, 0)) < index(Position::new(-1, -1, 0)));
+}
+
+#[test]
+fn turning_reorders_queue() {
+    let mut queue = LoadQueue::new();
+    let center = Position::new(0, 0, 0);
+    let range = LoadRange::new(2, 2);
+    queue.update(center, range, looking(Vec3::new(1., 0., 0.)), 2.);
+
+    // a small turn keeps the order
+    assert!(!queue.update(center, range, looking(Vec3::new(1., 0.05, 0.)), 2.));
+    assert!(!queue.update(center, range, looking(Vec3::new(-1., 0., 0.)), 2.));
+    let order = drain(&mut queue);
+    assert_eq!(order[1], Position::new(-1, 0, 0));
+    assert_eq!(order.len(), 81);
+}
+
+#[test]
+fn moving_refills_queue_around_new_center() {
+    let mut queue = LoadQueue::new();
+    let range = LoadRange::new(1, 1);
+    queue.update(Position::new(0, 0, 0), range, LoadFocus::default(), 2.);
+    assert_eq!(queue.pop(|_| false), None);
+    assert!(queue.is_empty());
+
+    let center = Position::new(5, 0, 0);
+    let focus = LoadFocus {
+        eye: eye() + Vec3::new(5. * CHUNK_REAL_SIZE as f32, 0., 0.),
+        ..LoadFocus::default()
+    };
+    assert!(queue.update(center, range, focus, 2.));
+
+    let order = drain(&mut queue);
+    assert_eq!(order.len(), 19);
+    assert_eq!(order[0], center);
+    assert_eq!(order[1], center + Position::new(0, 0, -1));
+    assert!(order.iter().all(|pos| (pos.x - center.x).abs() <= 1));
+}
+
+#[test]
+fn moving_prefetches_path_and_defers_chunks_behind() {
+    let mut queue = LoadQueue::new();
+    let center = Position::new(0, 0, 0);
+    let focus = LoadFocus {
+        velocity: Vec3::new(50., 0., 0.),
+        ..looking(Vec3::new(0., 0., -1.))
+    };
+
+    // 2 seconds at 50 voxels per second reach 6 chunks ahead
+    let path = LoadQueue::get_path(center, &focus, 2.);
+    assert_eq!(path.first(), Some(&center));
+    assert_eq!(path.last(), Some(&Position::new(6, 0, 0)));
+
+    queue.update(center, LoadRange::new(2, 2), focus, 2.);
+    assert!(queue.is_in_range(Position::new(7, 1, 0)));
+    assert!(!queue.is_in_range(Position::new(-4, 0, 0)));
+
+    let order = drain(&mut queue);
+    assert!(order.contains(&Position::new(6, 1, 0)));
+    let index = |pos: Position| order.iter().position(|p| *p == pos).unwrap();
+    assert!(index(Position::new(2, 0, 0)) < index(Position::new(-2, 0, 0)));
+    assert!(index(Position::new(2, 0, 0)) < index(Position::new(0, 0, -2)));
+
+    let mut still = LoadQueue::new();
+    still.update(
+        center,
+        LoadRange::new(2, 2),
+        looking(Vec3::new(0., 0., -1.)),
+        2.,
+    );
+    assert!(!still.is_in_range(Position::new(6, 0, 0)));
+    assert_eq!(still.len(), 81);
+}
+
+#[test]
+fn range_is_a_flattened_ellipsoid() {
+    let range = LoadRange::new(3, 1);
+    assert!(range.contains(Position::new(3, 0, 0), 0.));
+    assert!(range.contains(Position::new(2, 0, 2), 0.));
+    assert!(!range.contains(Position::new(3, 0, 3), 0.));
+    assert!(range.contains(Position::new(0, 1, 0), 0.));
+    assert!(!range.contains(Position::new(0, 2, 0), 0.));
+    assert!(range.contains(Position::new(0, 2, 0), 1.));
+
+    let center = Position::new(10, -4, 2);
+    let positions: Vec<Position> = range.iter_around(center).collect();
+    assert_eq!(positions.len(), 79);
+    assert!(positions.contains(&center));
+    assert!(positions.iter().all(|pos| (pos.y - center.y).abs() <= 1));
+
+    let mut queue = LoadQueue::new();
+    queue.update(center, range, LoadFocus::default(), 2.);
+    assert!(queue.is_in_range(center + Position::new(0, 2, 0)));
+    assert!(!queue.is_in_range(center + Position::new(0, 3, 0)));
+    assert!(queue.is_in_range(center + Position::new(4, 0, 0)));
+    assert!(!queue.is_in_range(center + Position::new(4, 0, 4)));
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    tests::TempDir,
+    utils::position::Position,
+    world::{
+        chunk::Chunk,
+        core::loader::ChunkLoader,
+        generator::{Generator, TerrainGenerator},
+        region::{RegionStore, SaveMode},
+    },
+};
+
+fn temp_loader(name: &str, worker_count: usize) -> (ChunkLoader, TempDir) {
+    let dir = TempDir::new(&format!("loader-{}", name));
+
+    let generator: Arc<dyn TerrainGenerator> = Arc::new(Generator::with_seed(3));
+    let store = Arc::new(RegionStore::new(&dir, SaveMode::Delta));
+    (ChunkLoader::new(generator, store, worker_count), dir)
+}
+
+/// Collects until nothing is pending anymore.
+fn collect_all(loader: &mut ChunkLoader) -> Vec<Chunk> {
+    let start = Instant::now();
+    let mut chunks = vec![];
+    while loader.get_pending_count() > 0 {
+        assert!(start.elapsed() < Duration::from_secs(30), "loader stalled");
+        chunks.extend(loader.collect());
+        thread::sleep(Duration::from_millis(1));
+    }
+    chunks
+}
+
+#[test]
+fn requested_chunks_are_collected() {
+    let (mut loader, _dir) = temp_loader("collect", 2);
+    let positions: Vec<Position> = Position::new(0, 0, 0).iter_neighbors(true).collect();
+    for pos in positions.iter() {
+        loader.request(*pos);
+        loader.request(*pos);
+    }
+    assert_eq!(loader.get_pending_count(), positions.len());
+
+    let chunks = collect_all(&mut loader);
+    assert_eq!(chunks.len(), positions.len());
+
+    let generator = Generator::with_seed(3);
+    for chunk in chunks {
+        assert!(positions.contains(&chunk.get_position()));
+        let mut expected = Chunk::new(chunk.get_position());
+        expected.generate_voxels(&generator);
+        assert!(chunk
+            .get_voxels()
+            .iter()
+            .zip(expected.get_voxels())
+            .all(|(a, b)| a.value.to_bits() == b.value.to_bits()));
+    }
+}
+
+#[test]
+fn cancelled_chunks_are_not_collected() {
+    let (mut loader, _dir) = temp_loader("cancel", 1);
+    for x in 0..20 {
+        loader.request(Position::new(x, 0, 0));
+    }
+
+    let cancelled = loader.cancel(|pos| pos.x < 3);
+    assert_eq!(cancelled, 17);
+    assert_eq!(loader.get_pending_count(), 3);
+    loader.forget(Position::new(2, 0, 0));
+
+    let mut collected: Vec<i64> = collect_all(&mut loader)
+        .iter()
+        .map(|chunk| chunk.get_position().x)
+        .collect();
+    collected.sort_unstable();
+    assert_eq!(collected, vec![0, 1]);
+}
@@ -0,0 +1,92 @@
+use crate::{
+    tests::{load_around, temp_core_with, TempDir},
+    utils::position::Position,
+    world::{
+        brush::{Brush, BrushMode},
+        core::WorldCore,
+        generator::flat::FlatGenerator,
+        voxel::{
+            material::{
+                Material, MaterialRegistry, BEDROCK, GRANITE, GRASS, GRAVEL, ROCK, SAND, SANDSTONE,
+                SLATE, SNOW,
+            },
+            VoxelId,
+        },
+    },
+};
+
+#[test]
+fn default_materials_match_ids() {
+    let materials = MaterialRegistry::default();
+    let ids = [
+        ROCK, GRASS, SAND, SANDSTONE, SNOW, SLATE, GRAVEL, GRANITE, BEDROCK,
+    ];
+    let names = [
+        "rock",
+        "grass",
+        "sand",
+        "sandstone",
+        "snow",
+        "slate",
+        "gravel",
+        "granite",
+        "bedrock",
+    ];
+
+    assert_eq!(materials.len(), ids.len());
+    for (id, name) in ids.into_iter().zip(names) {
+        assert_eq!(materials.get(id).unwrap().name, name);
+        assert_eq!(materials.get_id(name), Some(id));
+    }
+}
+
+#[test]
+fn registered_materials_get_new_ids() {
+    let mut materials = MaterialRegistry::default();
+    let count = materials.len();
+
+    let id = materials.register(Material::new("ice", [0.7, 0.85, 1.], 0.4));
+    assert_eq!(id as usize, count);
+    assert_eq!(materials.get_color(id), [0.7, 0.85, 1.]);
+    assert!(materials.get(id + 1).is_none());
+}
+
+/// Core whose terrain is solid `material` far around the origin.
+fn core_of(name: &str, material: VoxelId) -> (WorldCore, TempDir) {
+    let (mut core, dir) = temp_core_with(name, Box::new(FlatGenerator::new(100., material)));
+    load_around(&mut core, Position::new(0, 0, 0));
+    (core, dir)
+}
+
+#[test]
+fn hardness_scales_digging() {
+    let center = Position::new(8, 8, 8);
+    let (mut sand, _sand_dir) = core_of("material-sand", SAND);
+    let (mut granite, _granite_dir) = core_of("material-granite", GRANITE);
+
+    let dig = Brush::sphere(BrushMode::Subtract, 4., 0.3);
+    let sand_report = sand.apply_brush(&dig, center);
+    let granite_report = granite.apply_brush(&dig, center);
+    assert_eq!(sand_report.changed, granite_report.changed);
+
+    let sand_value = sand.get_voxel(center).unwrap().value;
+    let granite_value = granite.get_voxel(center).unwrap().value;
+    assert!((1. - sand_value - 0.3 / 0.3).abs() < 1e-5);
+    assert!((1. - granite_value - 0.3 / 1.5).abs() < 1e-5);
+}
+
+#[test]
+fn bedrock_resists_digging() {
+    let (mut core, _dir) = core_of("material-bedrock", BEDROCK);
+
+    let dig = Brush::sphere(BrushMode::Subtract, 2., 1.);
+    let center = Position::new(8, 8, 8);
+    let report = core.apply_brush(&dig, center);
+    assert_eq!(report.changed, 0);
+    assert!(report.resisted.contains(&Position::new(8, 8, 8)));
+    assert!(report.resisted.contains(&Position::new(9, 8, 8)));
+    let chunk = core.get_chunk(Position::new(0, 0, 0)).unwrap();
+    assert!(!chunk.is_modified());
+    assert_eq!(core.player.inventory.get_total(), 0.);
+    assert!(chunk.get_voxels().iter().all(|voxel| voxel.value == 1.));
+}
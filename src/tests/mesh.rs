@@ -0,0 +1,229 @@
+use crate::{
+    utils::position::Position,
+    world::{
+        chunk::{neighbourhood::ChunkNeighbourhood, Chunk, CHUNK_REAL_SIZE, CHUNK_VOXELS_VOLUME},
+        voxel::{
+            material::{Material, MaterialRegistry},
+            voxels_to_vertex::{mesh_chunk, mesh_chunk_with, MesherSettings, Shading},
+            Voxel,
+        },
+    },
+};
+
+fn chunk_from_fn(pos: Position, value: impl Fn(Position) -> f32) -> Chunk {
+    let mut chunk = Chunk::new(pos);
+    let offset = pos.mul_scalar(CHUNK_REAL_SIZE as i64);
+    let voxels: Vec<Voxel> = (0..CHUNK_VOXELS_VOLUME)
+        .map(|i| Voxel {
+            value: value(Chunk::index_to_pos(i) + offset),
+            material: 0,
+        })
+        .collect();
+    chunk.set_voxels(&voxels);
+    chunk
+}
+
+/// Solid below `y = 8.5` inside the chunk, empty above.
+fn flat_chunk(pos: Position) -> Chunk {
+    let base_y = pos.y * CHUNK_REAL_SIZE as i64;
+    chunk_from_fn(pos, move |p| 8.5 - (p.y - base_y) as f32)
+}
+
+#[test]
+fn empty_chunk_has_no_triangles() {
+    let mesh = mesh_chunk(&chunk_from_fn(Position::new(0, 0, 0), |_| -0.1));
+    assert!(mesh.is_empty());
+    assert_eq!(mesh.vertex_count(), 0);
+}
+
+#[test]
+fn solid_chunk_has_no_triangles() {
+    let mesh = mesh_chunk(&chunk_from_fn(Position::new(0, 0, 0), |_| 1.));
+    assert!(mesh.is_empty());
+}
+
+#[test]
+fn flat_chunk_triangle_count() {
+    let mesh = mesh_chunk(&flat_chunk(Position::new(0, 0, 0)));
+
+    assert_eq!(mesh.triangle_count(), CHUNK_REAL_SIZE * CHUNK_REAL_SIZE * 2);
+    assert_eq!(mesh.normals.len(), mesh.vertex_count());
+    assert_eq!(mesh.colors.len(), mesh.vertex_count());
+}
+
+#[test]
+fn flat_chunk_shares_vertices() {
+    let mesh = mesh_chunk(&flat_chunk(Position::new(0, 0, 0)));
+    let indices = mesh.indices.as_ref().unwrap();
+
+    assert_eq!(mesh.vertex_count(), (CHUNK_REAL_SIZE + 1).pow(2));
+    assert!(indices.iter().all(|i| (*i as usize) < mesh.vertex_count()));
+}
+
+#[test]
+fn sphere_vertices_are_not_duplicated() {
+    let mesh = mesh_chunk(&chunk_from_fn(Position::new(0, 0, 0), sphere_value));
+
+    assert!(mesh.triangle_count() > 0);
+    assert!(mesh.vertex_count() * 4 < mesh.triangle_count() * 3);
+
+    let mut positions: Vec<[u32; 3]> = mesh
+        .positions
+        .iter()
+        .map(|p| [p[0].to_bits(), p[1].to_bits(), p[2].to_bits()])
+        .collect();
+    positions.sort();
+    positions.dedup();
+    assert_eq!(positions.len(), mesh.vertex_count());
+}
+
+#[test]
+fn flat_chunk_faces_up() {
+    let mesh = mesh_chunk(&flat_chunk(Position::new(0, 0, 0)));
+
+    for (position, normal) in mesh.positions.iter().zip(mesh.normals.iter()) {
+        assert!((position[1] - 8.5).abs() < 1e-5);
+        assert!((normal[1] - 1.).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn mesh_is_in_world_coordinates() {
+    let pos = Position::new(-2, 1, 3);
+    let mesh = mesh_chunk(&flat_chunk(pos));
+    let size = CHUNK_REAL_SIZE as f32;
+
+    assert!(!mesh.is_empty());
+    for p in mesh.positions.iter() {
+        assert!(p[0] >= pos.x as f32 * size && p[0] <= (pos.x + 1) as f32 * size);
+        assert!((p[1] - (pos.y as f32 * size + 8.5)).abs() < 1e-4);
+        assert!(p[2] >= pos.z as f32 * size && p[2] <= (pos.z + 1) as f32 * size);
+    }
+}
+
+fn sphere_value(p: Position) -> f32 {
+    let d = p - Position::new(8, 8, 8);
+    5.3 - ((d.x * d.x + d.y * d.y + d.z * d.z) as f32).sqrt()
+}
+
+#[test]
+fn flat_shading_is_not_indexed() {
+    let settings = MesherSettings {
+        shading: Shading::Flat,
+        ..MesherSettings::default()
+    };
+    let chunk = chunk_from_fn(Position::new(0, 0, 0), sphere_value);
+    let flat = mesh_chunk_with(
+        &ChunkNeighbourhood::alone(&chunk),
+        &MaterialRegistry::default(),
+        settings,
+    );
+    let smooth = mesh_chunk(&chunk);
+
+    assert!(flat.indices.is_none());
+    assert_eq!(flat.vertex_count(), flat.triangle_count() * 3);
+    assert_eq!(flat.triangle_count(), smooth.triangle_count());
+}
+
+#[test]
+fn colors_come_from_materials() {
+    let mut materials = MaterialRegistry::new();
+    materials.register(Material::new("red", [1., 0., 0.], 1.));
+    let settings = MesherSettings {
+        color_variation: 0.,
+        ..MesherSettings::default()
+    };
+
+    let chunk = chunk_from_fn(Position::new(0, 0, 0), sphere_value);
+    let mesh = mesh_chunk_with(&ChunkNeighbourhood::alone(&chunk), &materials, settings);
+
+    assert!(!mesh.is_empty());
+    assert!(mesh.colors.iter().all(|color| *color == [1., 0., 0.]));
+}
+
+#[test]
+fn smooth_normals_point_outside() {
+    let mesh = mesh_chunk(&chunk_from_fn(Position::new(0, 0, 0), sphere_value));
+
+    for (p, n) in mesh.positions.iter().zip(mesh.normals.iter()) {
+        let outward = [p[0] - 8., p[1] - 8., p[2] - 8.];
+        let length = (outward[0].powi(2) + outward[1].powi(2) + outward[2].powi(2)).sqrt();
+        let cos = (outward[0] * n[0] + outward[1] * n[1] + outward[2] * n[2]) / length;
+        assert!(cos > 0.9, "normal {:?} at {:?}", n, p);
+    }
+}
+
+#[test]
+fn smooth_normals_sample_neighbour_chunks() {
+    let value = |p: Position| 8.5 - p.y as f32 + 0.01 * (p.x * p.x) as f32;
+    let chunks: Vec<Chunk> = Position::new(0, 0, 0)
+        .iter_neighbors(true)
+        .map(|pos| chunk_from_fn(pos, value))
+        .collect();
+    let chunk = chunks
+        .iter()
+        .find(|chunk| chunk.get_position() == Position::new(0, 0, 0))
+        .unwrap();
+    let neighbourhood = ChunkNeighbourhood::new(chunk, |pos| {
+        chunks.iter().find(|chunk| chunk.get_position() == pos)
+    });
+    assert!(neighbourhood.is_complete());
+
+    let with_neighbours = mesh_chunk_with(
+        &neighbourhood,
+        &MaterialRegistry::default(),
+        MesherSettings::default(),
+    );
+    let without_neighbours = mesh_chunk(chunk);
+
+    let border = CHUNK_REAL_SIZE as f32;
+    let expected_x = -0.02 * border / (1. + (0.02 * border).powi(2)).sqrt();
+
+    let mut border_vertices = 0;
+    for i in 0..with_neighbours.vertex_count() {
+        if with_neighbours.positions[i][0] != border {
+            continue;
+        }
+        border_vertices += 1;
+        assert!((with_neighbours.normals[i][0] - expected_x).abs() < 1e-5);
+        assert!((without_neighbours.normals[i][0] - expected_x).abs() > 1e-3);
+    }
+    assert!(border_vertices > 0);
+}
+
+#[test]
+fn neighbourhood_reads_neighbour_voxels() {
+    let center = chunk_from_fn(Position::new(0, 0, 0), |_| 1.);
+    let east = chunk_from_fn(Position::new(1, 0, 0), |_| 2.);
+    let south_down = chunk_from_fn(Position::new(0, -1, -1), |_| 3.);
+
+    let neighbourhood = ChunkNeighbourhood::new(&center, |pos| match pos {
+        p if p == east.get_position() => Some(&east),
+        p if p == south_down.get_position() => Some(&south_down),
+        _ => None,
+    });
+
+    let size = CHUNK_REAL_SIZE as i64;
+    let value = |pos: Position| neighbourhood.get_voxel(pos).map(|v| v.value);
+
+    assert_eq!(value(Position::new(size, 3, 3)), Some(1.));
+    assert_eq!(value(Position::new(size + 1, 3, 3)), Some(2.));
+    assert_eq!(value(Position::new(2 * size - 1, 0, 0)), Some(2.));
+    assert_eq!(value(Position::new(4, -1, -size)), Some(3.));
+    assert_eq!(value(Position::new(2 * size, 0, 0)), None);
+    assert_eq!(neighbourhood.get_missing_sampled(), 0);
+
+    assert_eq!(value(Position::new(-1, 0, 0)), None);
+    let west = ChunkNeighbourhood::offset_to_index(Position::new(-1, 0, 0));
+    assert_eq!(neighbourhood.get_missing_sampled(), 1 << west);
+    assert!(!neighbourhood.is_complete());
+}
+
+#[test]
+fn neighbourhood_index_round_trip() {
+    for offset in Position::new(0, 0, 0).iter_neighbors(true) {
+        let index = ChunkNeighbourhood::offset_to_index(offset);
+        assert!(index < 27);
+        assert_eq!(ChunkNeighbourhood::index_to_offset(index), offset);
+    }
+}
@@ -1,2 +1,107 @@
+pub mod brush;
+pub mod edit_log;
+pub mod generator;
+pub mod history;
+pub mod inventory;
+pub mod load_queue;
+pub mod loader;
+pub mod material;
+pub mod mesh;
 pub mod position;
+pub mod raycast;
+pub mod region;
 pub mod vector;
+pub mod world;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    player::Player,
+    utils::position::Position,
+    world::{
+        core::WorldCore,
+        generator::{Generator, TerrainGenerator},
+        region::{RegionStore, SaveMode},
+    },
+};
+
+/// Directory of a test under the system temp directory. It is removed when
+/// dropped, so it goes away even when the test fails.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("adventure-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        Self { path }
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Core of a world generated with seed 3, saved in a temporary directory.
+pub fn temp_core(name: &str) -> (WorldCore, TempDir) {
+    temp_core_with(name, Box::new(Generator::with_seed(3)))
+}
+
+pub fn temp_core_with(name: &str, generator: Box<dyn TerrainGenerator>) -> (WorldCore, TempDir) {
+    let dir = TempDir::new(&format!("world-{}", name));
+
+    let core = WorldCore::new(
+        generator,
+        RegionStore::new(&dir, SaveMode::Delta),
+        Player::new((800., 600.)),
+    );
+    (core, dir)
+}
+
+/// Loads the chunk at `center` and its 26 neighbours.
+pub fn load_around(core: &mut WorldCore, center: Position) {
+    for pos in center.iter_neighbors(true) {
+        core.load_chunk_now(pos);
+    }
+}
+
+/// Highest solid voxel of the column at `x`, `z`.
+pub fn surface_below(core: &WorldCore, x: i64, z: i64) -> Position {
+    (-16..16)
+        .rev()
+        .map(|y| Position::new(x, y, z))
+        .find(|pos| core.get_voxel(*pos).unwrap().value > 0.)
+        .expect("no terrain below")
+}
+
+/// Density of the voxels in the cube of `extent` voxels around `center`.
+pub fn world_density(core: &WorldCore, center: Position, extent: i64) -> f64 {
+    let mut density = 0.;
+    for x in -extent..=extent {
+        for y in -extent..=extent {
+            for z in -extent..=extent {
+                density += core
+                    .get_voxel(center + Position::new(x, y, z))
+                    .unwrap()
+                    .get_density() as f64;
+            }
+        }
+    }
+    density
+}
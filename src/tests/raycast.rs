@@ -0,0 +1,123 @@
+use crate::{
+    utils::position::Position,
+    vec::Vec3,
+    world::{
+        raycast::{cast_ray, RayCast},
+        voxel::Voxel,
+    },
+};
+
+fn field(value: impl Fn(Position) -> f32) -> impl Fn(Position) -> Option<Voxel> {
+    move |pos| {
+        Some(Voxel {
+            value: value(pos),
+            material: 0,
+        })
+    }
+}
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+}
+
+fn assert_vec_close(a: Vec3<f32>, b: Vec3<f32>) {
+    assert_close(a.x, b.x);
+    assert_close(a.y, b.y);
+    assert_close(a.z, b.z);
+}
+
+/// Solid below `y = 4.5`.
+fn ground(pos: Position) -> f32 {
+    4.5 - pos.y as f32
+}
+
+#[test]
+fn hits_ground_straight_down() {
+    let from = Vec3::new(0.5, 10.2, 0.5);
+    let hit = cast_ray(from, Vec3::new(0., -1., 0.), 32., field(ground))
+        .hit
+        .unwrap();
+
+    assert_close(hit.distance, 5.7);
+    assert_vec_close(hit.position, Vec3::new(0.5, 4.5, 0.5));
+    assert_vec_close(hit.normal, Vec3::new(0., 1., 0.));
+    assert_eq!(hit.cell, Position::new(0, 4, 0));
+    assert!(hit.voxel.value >= 0.);
+}
+
+#[test]
+fn hits_ground_diagonally() {
+    let from = Vec3::new(0.3, 10.3, 0.7);
+    let dir = Vec3::new(1., -1., 0.5);
+    let hit = cast_ray(from, dir, 32., field(ground)).hit.unwrap();
+
+    assert_close(hit.distance, 5.8 * dir.length());
+    assert_close(hit.position.y, 4.5);
+    assert_vec_close(hit.position, from + dir.normalize() * hit.distance);
+    assert_vec_close(hit.normal, Vec3::new(0., 1., 0.));
+}
+
+#[test]
+fn hits_wall_in_negative_direction() {
+    let hit = cast_ray(
+        Vec3::new(0.5, 0.5, 0.5),
+        Vec3::new(-1., 0., 0.),
+        32.,
+        field(|pos| -6.5 - pos.x as f32),
+    )
+    .hit
+    .unwrap();
+
+    assert_close(hit.distance, 7.);
+    assert_eq!(hit.cell.x, -7);
+    assert_vec_close(hit.normal, Vec3::new(1., 0., 0.));
+}
+
+#[test]
+fn misses_past_max_distance() {
+    let cast = cast_ray(
+        Vec3::new(0.5, 0.5, 0.5),
+        Vec3::new(1., 0., 0.),
+        3.,
+        field(|pos| pos.x as f32 - 9.5),
+    );
+    assert!(cast.hit.is_none());
+    assert!(!cast.crossed_unloaded);
+}
+
+#[test]
+fn starting_inside_terrain_hits_at_origin() {
+    let hit = cast_ray(
+        Vec3::new(0.5, 2., 0.5),
+        Vec3::new(0., 1., 0.),
+        32.,
+        field(ground),
+    )
+    .hit
+    .unwrap();
+    assert_close(hit.distance, 0.);
+}
+
+#[test]
+fn reports_unloaded_chunks() {
+    let wall = field(|pos| pos.x as f32 - 9.5);
+    let get_voxel = |pos: Position| {
+        if (3..=5).contains(&pos.x) {
+            None
+        } else {
+            wall(pos)
+        }
+    };
+
+    let RayCast {
+        hit,
+        crossed_unloaded,
+    } = cast_ray(
+        Vec3::new(0.5, 0.5, 0.5),
+        Vec3::new(1., 0., 0.),
+        32.,
+        get_voxel,
+    );
+    assert!(crossed_unloaded);
+    assert_close(hit.unwrap().distance, 9.);
+}
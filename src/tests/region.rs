@@ -0,0 +1,251 @@
+use std::fs;
+
+use crate::{
+    player::inventory::Inventory,
+    tests::{load_around, surface_below, temp_core, temp_core_with, TempDir},
+    utils::position::Position,
+    world::{
+        brush::{Brush, BrushMode},
+        chunk::{Chunk, CHUNK_REAL_SIZE, CHUNK_VOXELS_VOLUME},
+        generator::{Generator, GeneratorParams},
+        region::{RegionStore, SaveMode, WorldMeta, REGION_SIZE},
+        voxel::{
+            material::{GRANITE, SAND, SNOW},
+            Voxel,
+        },
+    },
+};
+
+fn temp_store(name: &str, mode: SaveMode) -> (RegionStore, TempDir) {
+    let dir = TempDir::new(&format!("region-{}", name));
+    (RegionStore::new(&dir, mode), dir)
+}
+
+fn make_chunk(pos: Position, seed: f32) -> Chunk {
+    let mut chunk = Chunk::new(pos);
+    let voxels: Vec<Voxel> = (0..CHUNK_VOXELS_VOLUME)
+        .map(|i| Voxel {
+            value: (i as f32 * 0.37 + seed).sin(),
+            material: (i as u32 + seed as u32) % 8,
+        })
+        .collect();
+    chunk.set_voxels(&voxels);
+    chunk
+}
+
+fn assert_same_voxels(a: &Chunk, b: &Chunk) {
+    for (va, vb) in a.get_voxels().iter().zip(b.get_voxels().iter()) {
+        assert_eq!(va.value.to_bits(), vb.value.to_bits());
+        assert_eq!(va.material, vb.material);
+    }
+}
+
+#[test]
+fn region_pos_negative() {
+    assert_eq!(
+        RegionStore::get_region_pos(Position::new(-1, 0, REGION_SIZE)),
+        Position::new(-1, 0, 1)
+    );
+    assert_eq!(
+        RegionStore::get_in_region_pos(Position::new(-1, 0, REGION_SIZE + 3)),
+        Position::new(REGION_SIZE - 1, 0, 3)
+    );
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let (store, _dir) = temp_store("round-trip", SaveMode::Full);
+    let generator = Generator::new();
+
+    let positions = [
+        Position::new(0, 0, 0),
+        Position::new(5, 2, 31),
+        Position::new(-1, -1, -1),
+        Position::new(-33, 40, -64),
+        Position::new(100, -7, 12),
+    ];
+
+    for (i, pos) in positions.iter().enumerate() {
+        store
+            .save_chunk(&make_chunk(*pos, i as f32), &generator)
+            .unwrap();
+    }
+
+    for (i, pos) in positions.iter().enumerate() {
+        let mut loaded = Chunk::new(*pos);
+        assert!(store.load_chunk(&mut loaded, &generator).unwrap());
+        assert_same_voxels(&make_chunk(*pos, i as f32), &loaded);
+    }
+}
+
+#[test]
+fn save_overwrites_previous_version() {
+    let (store, _dir) = temp_store("overwrite", SaveMode::Full);
+    let generator = Generator::new();
+    let pos = Position::new(-3, 1, -40);
+
+    store.save_chunk(&make_chunk(pos, 1.), &generator).unwrap();
+    store.save_chunk(&make_chunk(pos, 2.), &generator).unwrap();
+
+    let mut loaded = Chunk::new(pos);
+    assert!(store.load_chunk(&mut loaded, &generator).unwrap());
+    assert_same_voxels(&make_chunk(pos, 2.), &loaded);
+}
+
+#[test]
+fn batch_save_keeps_other_chunks_of_region() {
+    let (store, _dir) = temp_store("batch", SaveMode::Full);
+    let generator = Generator::new();
+    let kept = make_chunk(Position::new(1, 1, 1), 9.);
+    store.save_chunk(&kept, &generator).unwrap();
+
+    let chunks: Vec<Chunk> = [
+        Position::new(0, 0, 0),
+        Position::new(2, 0, 5),
+        Position::new(-1, 0, 0),
+        Position::new(REGION_SIZE, 3, 0),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, pos)| make_chunk(*pos, i as f32))
+    .collect();
+    store.save_chunks(&chunks, &generator).unwrap();
+
+    for chunk in chunks.iter().chain([&kept]) {
+        let mut loaded = Chunk::new(chunk.get_position());
+        assert!(store.load_chunk(&mut loaded, &generator).unwrap());
+        assert_same_voxels(chunk, &loaded);
+    }
+}
+
+#[test]
+fn load_missing_chunk() {
+    let (store, _dir) = temp_store("missing", SaveMode::Full);
+    let generator = Generator::new();
+
+    let mut chunk = Chunk::new(Position::new(1, 1, 1));
+    assert!(!store.load_chunk(&mut chunk, &generator).unwrap());
+
+    store
+        .save_chunk(&make_chunk(Position::new(0, 0, 0), 0.), &generator)
+        .unwrap();
+    assert!(!store.load_chunk(&mut chunk, &generator).unwrap());
+}
+
+#[test]
+fn dig_marks_chunk_modified() {
+    let (mut core, _dir) = temp_core("region-modified");
+    load_around(&mut core, Position::new(0, -1, 0));
+    let center = surface_below(&core, 8, 8);
+    let pos = Chunk::get_chunk_pos(center);
+    assert!(!core.get_chunk(pos).unwrap().is_modified());
+
+    core.apply_brush(&Brush::sphere(BrushMode::Subtract, 6., 1.), center);
+    assert!(core.get_chunk(pos).unwrap().is_modified());
+}
+
+#[test]
+fn delta_round_trip() {
+    let (store, _dir) = temp_store("delta", SaveMode::Delta);
+    let (mut core, _core_dir) = temp_core_with("region-delta", Box::new(Generator::new()));
+
+    for pos in [Position::new(2, 0, -5), Position::new(-40, -1, 33)] {
+        core.load_chunk_now(pos);
+        let center =
+            Chunk::index_to_pos(CHUNK_VOXELS_VOLUME / 2) + pos.mul_scalar(CHUNK_REAL_SIZE as i64);
+        core.apply_brush(&Brush::sphere(BrushMode::Subtract, 6., 0.5), center);
+        let chunk = core.get_chunk(pos).unwrap();
+        store.save_chunk(chunk, core.get_generator()).unwrap();
+
+        let mut loaded = Chunk::new(pos);
+        assert!(store.load_chunk(&mut loaded, core.get_generator()).unwrap());
+        assert_same_voxels(chunk, &loaded);
+        assert!(!loaded.is_modified());
+    }
+}
+
+#[test]
+fn delta_smaller_than_full() {
+    let (full_store, full_dir) = temp_store("size-full", SaveMode::Full);
+    let (delta_store, delta_dir) = temp_store("size-delta", SaveMode::Delta);
+    let (mut core, _core_dir) = temp_core_with("region-size", Box::new(Generator::new()));
+    core.player.inventory = Inventory::unlimited();
+
+    let pos = Position::new(0, 0, 0);
+    core.load_chunk_now(pos);
+    let fill = Brush {
+        material: SAND,
+        ..Brush::sphere(BrushMode::Add, 3., 1.)
+    };
+    core.apply_brush(&fill, Position::new(8, 8, 8));
+    let chunk = core.get_chunk(pos).unwrap();
+
+    full_store.save_chunk(chunk, core.get_generator()).unwrap();
+    delta_store.save_chunk(chunk, core.get_generator()).unwrap();
+
+    let file_name = "0.0.0.region";
+    let full_size = fs::metadata(full_dir.join(file_name)).unwrap().len();
+    let delta_size = fs::metadata(delta_dir.join(file_name)).unwrap().len();
+    assert!(delta_size * 4 < full_size);
+}
+
+#[test]
+fn meta_round_trip() {
+    let (store, _dir) = temp_store("meta", SaveMode::Delta);
+    assert_eq!(store.load_meta().unwrap(), None);
+
+    let params = GeneratorParams {
+        seed: 1234,
+        scale: 0.123456789,
+        noise_threshold: 0.7,
+        grass_level: 0.01,
+        grass_material: SNOW,
+        rock_material: GRANITE,
+        color_variation: 0.125,
+        biomes: true,
+        caves: true,
+    };
+    for terrain_hash in [None, Some(0xfedc_ba98_7654_3210)] {
+        let world_meta = WorldMeta {
+            params,
+            terrain_hash,
+        };
+        store.save_meta(&world_meta).unwrap();
+        assert_eq!(store.load_meta().unwrap(), Some(world_meta));
+    }
+}
+
+#[test]
+fn inventory_round_trip() {
+    let (store, _dir) = temp_store("inventory", SaveMode::Delta);
+    assert_eq!(store.load_inventory().unwrap(), None);
+
+    let mut inventory = Inventory::new();
+    inventory.add(SAND, 12.375);
+    inventory.add(GRANITE, -0.5);
+    store.save_inventory(&inventory).unwrap();
+    assert_eq!(store.load_inventory().unwrap(), Some(inventory));
+
+    store.save_inventory(&Inventory::unlimited()).unwrap();
+    assert_eq!(
+        store.load_inventory().unwrap(),
+        Some(Inventory::unlimited())
+    );
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let (store, dir) = temp_store("versions", SaveMode::Delta);
+    fs::create_dir_all(&dir).unwrap();
+
+    fs::write(dir.join("world.meta"), "version=6\nseed=5\n").unwrap();
+    assert!(store.load_meta().is_err());
+
+    let mut data = b"ADVR".to_vec();
+    for value in [2u32, 0] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    fs::write(dir.join("0.0.0.region"), data).unwrap();
+    let mut chunk = Chunk::new(Position::new(0, 0, 0));
+    assert!(store.load_chunk(&mut chunk, &Generator::new()).is_err());
+}
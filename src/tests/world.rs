@@ -0,0 +1,745 @@
+use std::{fs, io};
+
+use crate::{
+    tests::{load_around, surface_below, temp_core, temp_core_with, world_density, TempDir},
+    utils::position::Position,
+    vec::Vec3,
+    world::{
+        brush::{Brush, BrushMode, BrushShape, Falloff},
+        chunk::{Chunk, CHUNK_REAL_SIZE},
+        core::WorldCore,
+        edit_log::{Edit, EditLog, EDIT_LOG_FILE},
+        generator::{
+            flat::FlatGenerator,
+            graph::{hash_source, DEFAULT_TERRAIN_GRAPH},
+        },
+        region::{RegionStore, SaveMode},
+        voxel::{
+            material::{BEDROCK, SAND, SNOW},
+            voxels_to_vertex::{mesh_chunk_with, MesherSettings},
+            Voxel,
+        },
+        World, TERRAIN_GRAPH_FILE,
+    },
+};
+
+#[test]
+fn load_chunks_without_gpu() {
+    let (mut core, _dir) = temp_core("load");
+    core.set_render_distance(1);
+
+    while core.load_chunks() {}
+
+    assert!(core.get_chunk(Position::new(0, 0, 0)).is_some());
+    let dirty = core.take_dirty_chunks();
+    assert_eq!(dirty.len(), core.get_chunks().count());
+    assert!(core.take_dirty_chunks().is_empty());
+}
+
+#[test]
+fn moving_away_cancels_pending_chunks() {
+    let (mut core, _dir) = temp_core("cancel");
+    core.set_render_distance(1);
+    core.set_vertical_render_distance(1);
+    core.load_chunks();
+    assert!(core.get_pending_chunk_count() > 0);
+
+    core.player.set_pos(Vec3::new(1000., 0., 0.));
+    let far = core.player.get_chunk_pos();
+    while core.load_chunks() {}
+
+    assert!(core.get_chunk(far).is_some());
+    for chunk in core.get_chunks() {
+        let delta = chunk.get_position() - far;
+        assert!(delta.x.abs().max(delta.y.abs()).max(delta.z.abs()) <= 2);
+    }
+}
+
+#[test]
+fn teleport_unloads_every_chunk_at_once() {
+    let (mut core, _dir) = temp_core("teleport");
+    core.set_render_distance(2);
+    core.set_vertical_render_distance(1);
+    core.load_chunks();
+    let center = core.player.get_chunk_pos();
+    load_around(&mut core, center);
+    let loaded = core.get_chunks().count();
+    assert!(loaded > 0);
+    assert_eq!(core.unload_chunks(), 0);
+
+    core.player.set_pos(Vec3::new(5000., 0., 0.));
+    core.load_chunks();
+    assert_eq!(core.unload_chunks(), loaded);
+    assert_eq!(core.get_chunks().count(), 0);
+}
+
+#[test]
+fn player_chunk_rounds_down_below_zero() {
+    let (mut core, _dir) = temp_core("negative-eye");
+    core.player.set_pos(Vec3::new(-1., -20., 5.));
+    assert_eq!(core.player.get_chunk_pos(), Position::new(-1, -2, 0));
+    assert_eq!(
+        core.player.get_chunk_pos(),
+        Chunk::get_chunk_pos(Position::new(-1, -20, 5))
+    );
+}
+
+#[test]
+fn velocity_and_missed_chunks_are_tracked() {
+    let (mut core, _dir) = temp_core("velocity");
+    core.toggle_generation();
+    core.player.set_pos(Vec3::new(8., 8., 8.));
+    core.update(0.1);
+
+    for _ in 0..20 {
+        core.player.translate_abs(Vec3::new(2., 0., 0.));
+        core.update(0.1);
+    }
+    let velocity = core.get_velocity();
+    assert!((velocity.x - 20.).abs() < 1., "{:?}", velocity);
+    assert!(velocity.y.abs() < 0.1 && velocity.z.abs() < 0.1);
+
+    // nothing is loaded with generation off, every chunk entered is missed
+    let stats = core.get_load_stats();
+    assert!(stats.chunks_entered >= 2);
+    assert_eq!(stats.chunks_missed, stats.chunks_entered);
+
+    // a teleport is not a velocity
+    core.player.set_pos(Vec3::new(5000., 8., 8.));
+    core.update(0.1);
+    assert!(core.get_velocity().x < 20.);
+}
+
+/// Chunk `distance` voxels from the player along the look direction.
+fn chunk_along_look(core: &WorldCore, distance: f32) -> Position {
+    let pos = core.player.get_pos() + core.player.get_look_dir() * distance;
+    Chunk::get_chunk_pos(Position::new(
+        pos.x.floor() as i64,
+        pos.y.floor() as i64,
+        pos.z.floor() as i64,
+    ))
+}
+
+#[test]
+fn memory_budget_evicts_least_recently_viewed_chunks() {
+    let (mut core, _dir) = temp_core("evict");
+    core.toggle_generation();
+    core.player.set_pos(Vec3::new(8., 8., 8.));
+    let (oldest, older) = (chunk_along_look(&core, -48.), chunk_along_look(&core, -32.));
+    let front = chunk_along_look(&core, 48.);
+
+    core.load_chunk_now(oldest);
+    let edited = oldest.mul_scalar(CHUNK_REAL_SIZE as i64) + Position::new(4, 4, 4);
+    let voxel = Voxel {
+        value: 0.7,
+        material: SAND,
+    };
+    core.set_voxel(edited, voxel);
+    core.update(0.1);
+    core.load_chunk_now(older);
+    core.load_chunk_now(front);
+    core.set_mesh_bytes(front, 400);
+    core.set_mesh_bytes(front, 1000);
+
+    let stats = core.get_memory_stats();
+    assert_eq!(stats.resident_chunks, 3);
+    assert_eq!(stats.mesh_bytes, 1000);
+    let chunk_bytes = stats.voxel_bytes / 3;
+
+    core.set_memory_budget(chunk_bytes * 2 + 1000);
+    core.update(0.1);
+    assert!(core.get_chunk(oldest).is_none());
+    assert!(core.get_chunk(older).is_some());
+    assert!(core.take_unloaded_chunks().contains(&oldest));
+
+    // chunks in view stay over the budget
+    core.set_memory_budget(0);
+    core.update(0.1);
+    assert!(core.get_chunk(older).is_none());
+    assert!(core.get_chunk(front).is_some());
+
+    let stats = core.get_memory_stats();
+    assert_eq!(stats.resident_chunks, 1);
+    assert_eq!(stats.voxel_bytes, chunk_bytes);
+    assert_eq!(stats.mesh_bytes, 1000);
+    assert_eq!(stats.evicted_chunks, 2);
+    assert_eq!(stats.evicted_modified_chunks, 1);
+
+    // the edit was saved before eviction
+    core.load_chunk_now(oldest);
+    assert_eq!(core.get_voxel(edited).unwrap().value, 0.7);
+}
+
+#[test]
+fn dig_and_fill() {
+    let (mut core, _dir) = temp_core("dig");
+    load_around(&mut core, Position::new(0, -1, 0));
+    core.take_dirty_chunks();
+
+    let hit = surface_below(&core, 8, 8);
+    let before = core.get_voxel(hit).unwrap().value;
+
+    core.dig(hit, 3., 0.5);
+    let dug = core.get_voxel(hit).unwrap().value;
+    assert!(dug < before);
+    assert!(core
+        .take_dirty_chunks()
+        .contains(&Chunk::get_chunk_pos(hit)));
+
+    let voxel = core.get_voxel(hit).unwrap();
+    core.player.inventory.add(voxel.material, 100.);
+    core.fill(hit, 3., voxel, 0.5);
+    assert!(core.get_voxel(hit).unwrap().value > dug);
+}
+
+#[test]
+fn edits_survive_unload() {
+    let (mut core, _dir) = temp_core("unload");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 4, 11);
+    core.dig(hit, 4., 1.);
+    let dug = core.get_voxel(hit).unwrap();
+
+    let chunk_pos = Chunk::get_chunk_pos(hit);
+    core.unload_chunk(chunk_pos);
+    assert!(core.get_voxel(hit).is_none());
+    assert!(core.take_unloaded_chunks().contains(&chunk_pos));
+
+    core.load_chunk_now(chunk_pos);
+    assert_eq!(
+        core.get_voxel(hit).unwrap().value.to_bits(),
+        dug.value.to_bits()
+    );
+}
+
+#[test]
+fn failed_save_keeps_chunk_modified() {
+    let (mut core, dir) = temp_core("save-error");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 4, 11);
+    core.dig(hit, 1., 1.);
+    let chunk_pos = Chunk::get_chunk_pos(hit);
+
+    // A directory in place of the temporary region file makes the write fail.
+    let region_pos = RegionStore::get_region_pos(chunk_pos);
+    let blocker = dir.join(format!(
+        "{}.{}.{}.region.tmp",
+        region_pos.x, region_pos.y, region_pos.z
+    ));
+    fs::create_dir_all(&blocker).unwrap();
+    assert!(core.save().is_err());
+    assert!(core.get_chunk(chunk_pos).unwrap().is_modified());
+
+    fs::remove_dir(&blocker).unwrap();
+    core.save().unwrap();
+    assert!(!core.get_chunk(chunk_pos).unwrap().is_modified());
+}
+
+#[test]
+fn save_keeps_inventory() {
+    let (mut core, dir) = temp_core("save-inventory");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 4, 11);
+    let material = core.get_voxel(hit).unwrap().material;
+    core.dig(hit, 3., 1.);
+    let carried = core.player.inventory.get_amount(material);
+    assert!(carried > 0.);
+    core.save().unwrap();
+
+    let store = RegionStore::new(&dir, SaveMode::Delta);
+    let inventory = store.load_inventory().unwrap().unwrap();
+    assert_eq!(inventory, core.player.inventory);
+    assert_eq!(inventory.get_amount(material), carried);
+}
+
+fn remesh_dirty(core: &mut WorldCore) -> Vec<Position> {
+    let dirty = core.take_dirty_chunks();
+    for pos in dirty.iter() {
+        let neighbourhood = core.get_neighbourhood(*pos).unwrap();
+        mesh_chunk_with(
+            &neighbourhood,
+            core.get_materials(),
+            MesherSettings::default(),
+        );
+        let missing = neighbourhood.get_missing_sampled();
+        core.set_missing_neighbours(*pos, missing);
+    }
+    dirty
+}
+
+#[test]
+fn remesh_when_missing_neighbour_arrives() {
+    let (mut core, _dir) = temp_core("remesh");
+    let pos = Position::new(0, -1, 0);
+
+    core.load_chunk_now(pos);
+    assert_eq!(remesh_dirty(&mut core), vec![pos]);
+
+    // not sampled by the mesher, only the chunks sharing a face are
+    core.load_chunk_now(pos + Position::new(1, 1, 1));
+    assert_eq!(remesh_dirty(&mut core), vec![pos + Position::new(1, 1, 1)]);
+
+    let east = pos + Position::new(1, 0, 0);
+    core.load_chunk_now(east);
+    let dirty = remesh_dirty(&mut core);
+    assert!(dirty.contains(&pos));
+    assert!(dirty.contains(&east));
+
+    core.load_chunk_now(pos + Position::new(0, 0, 2));
+    assert!(!remesh_dirty(&mut core).contains(&pos));
+}
+
+fn owner_voxels(core: &WorldCore, pos: Position) -> Vec<Voxel> {
+    Chunk::get_owning_chunks(pos)
+        .into_iter()
+        .map(|chunk_pos| {
+            let in_chunk_pos = pos - chunk_pos.mul_scalar(CHUNK_REAL_SIZE as i64);
+            core.get_chunk(chunk_pos)
+                .unwrap()
+                .get_voxel(in_chunk_pos)
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn owning_chunks_of_border_voxels() {
+    assert_eq!(
+        Chunk::get_owning_chunks(Position::new(3, 5, 7)),
+        vec![Position::new(0, 0, 0)]
+    );
+    assert_eq!(
+        Chunk::get_owning_chunks(Position::new(-16, 5, 7)),
+        vec![Position::new(-2, 0, 0), Position::new(-1, 0, 0)]
+    );
+    assert_eq!(Chunk::get_owning_chunks(Position::new(0, 16, -32)).len(), 8);
+    assert_eq!(
+        Chunk::get_chunk_pos(Position::new(-16, -1, 16)),
+        Position::new(-1, -1, 1)
+    );
+}
+
+#[test]
+fn set_voxel_writes_every_owner() {
+    let (mut core, _dir) = temp_core("set-voxel");
+    let corner = Position::new(16, 0, -16);
+    let voxel = Voxel {
+        value: 0.75,
+        material: SNOW,
+    };
+
+    core.set_voxel(corner, voxel);
+
+    let owners = owner_voxels(&core, corner);
+    assert_eq!(owners.len(), 8);
+    assert!(owners.iter().all(|v| v.value == 0.75 && v.material == SNOW));
+
+    let mut dirty = core.take_dirty_chunks();
+    dirty.sort();
+    let mut expected = Chunk::get_owning_chunks(corner);
+    expected.sort();
+    assert_eq!(dirty, expected);
+}
+
+#[test]
+fn set_voxel_dirties_chunks_of_neighbours() {
+    let (mut core, _dir) = temp_core("set-voxel-neighbours");
+    let west = Position::new(-1, 0, 0);
+    core.load_chunk_now(Position::new(0, 0, 0));
+    core.load_chunk_now(west);
+    core.take_dirty_chunks();
+
+    // only stored by chunk 0, but its west neighbour is on the shared border
+    let pos = Position::new(1, 5, 5);
+    assert_eq!(Chunk::get_owning_chunks(pos), vec![Position::new(0, 0, 0)]);
+    core.set_voxel(
+        pos,
+        Voxel {
+            value: 0.75,
+            material: SNOW,
+        },
+    );
+
+    let dirty = core.take_dirty_chunks();
+    assert!(dirty.contains(&west));
+    assert_eq!(dirty.len(), 2);
+}
+
+#[test]
+fn modify_voxels_keeps_borders_consistent() {
+    let (mut core, _dir) = temp_core("modify");
+    let min = Position::new(12, -4, 12);
+    let max = Position::new(20, 4, 20);
+
+    let mut calls = 0;
+    let changed = core.modify_voxels(min, max, |pos, voxel| {
+        calls += 1;
+        voxel.value += pos.x as f32;
+        true
+    });
+    assert_eq!(calls, 9 * 9 * 9);
+    assert_eq!(changed, calls);
+
+    for pos in [
+        Position::new(16, 0, 16),
+        Position::new(16, 3, 13),
+        Position::new(20, 0, 16),
+    ] {
+        let owners = owner_voxels(&core, pos);
+        assert!(owners.len() > 1);
+        assert!(owners
+            .iter()
+            .all(|v| v.value.to_bits() == owners[0].value.to_bits()));
+    }
+
+    let dirty = core.take_dirty_chunks();
+    assert_eq!(dirty.len(), 8);
+    assert!(core.take_dirty_chunks().is_empty());
+}
+
+#[test]
+fn cast_ray_hits_generated_terrain() {
+    let (mut core, _dir) = temp_core("raycast");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let surface = surface_below(&core, 8, 8);
+    let cast = core.cast_ray(Vec3::new(8., 15., 8.), Vec3::new(0., -1., 0.), 64.);
+    let hit = cast.hit.unwrap();
+
+    assert!(!cast.crossed_unloaded);
+    assert!(hit.position.y >= surface.y as f32);
+    assert!(hit.position.y <= surface.y as f32 + 1.);
+    assert!(hit.voxel.value >= 0.);
+    assert!(hit.normal.y > 0.);
+}
+
+#[test]
+fn undo_and_redo_dig_stroke() {
+    let (mut core, _dir) = temp_core("undo");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    // on a chunk corner, so the edit is shared by several chunks
+    let hit = surface_below(&core, 16, 16);
+    let before: Vec<Voxel> = owner_voxels(&core, hit);
+
+    core.begin_stroke();
+    core.dig(hit, 3., 0.5);
+    core.dig(hit, 3., 0.5);
+    core.end_stroke();
+    let dug = core.get_voxel(hit).unwrap().value;
+    assert!(dug < before[0].value);
+    assert_eq!(core.get_history().get_undo_count(), 1);
+
+    core.take_dirty_chunks();
+    assert!(core.undo());
+    assert!(owner_voxels(&core, hit)
+        .iter()
+        .all(|v| v.value.to_bits() == before[0].value.to_bits()));
+    assert!(!core.take_dirty_chunks().is_empty());
+    assert!(!core.undo());
+
+    assert!(core.redo());
+    assert_eq!(core.get_voxel(hit).unwrap().value.to_bits(), dug.to_bits());
+    assert!(!core.redo());
+}
+
+#[test]
+fn replaying_edit_log_rebuilds_world() {
+    let (mut core, dir) = temp_core("edit-log");
+    core.set_edit_log(EditLog::open(dir.join(EDIT_LOG_FILE)).unwrap());
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 5, 9);
+    let voxel = core.get_voxel(hit).unwrap();
+    core.dig(hit, 4., 1.);
+    core.fill(hit + Position::new(3, 0, 0), 3., voxel, 0.5);
+    core.save().unwrap();
+
+    let records = EditLog::read(dir.join(EDIT_LOG_FILE)).unwrap();
+    assert_eq!(records.len(), 2);
+    let brushes: Vec<Brush> = records
+        .iter()
+        .filter_map(|record| match &record.edit {
+            Edit::Brush { brush, .. } => Some(*brush),
+            Edit::Voxels(_) => None,
+        })
+        .collect();
+    assert_eq!(brushes[0].mode, BrushMode::Subtract);
+    assert_eq!(brushes[1].material, voxel.material);
+
+    let (mut replayed, _replayed_dir) = temp_core("edit-log-replay");
+    replayed.replay_edits(&records);
+
+    let mut edited = 0;
+    for offset in Position::new(0, 0, 0).iter_neighbors(true) {
+        for scale in 1..=3 {
+            let pos = hit + offset.mul_scalar(scale);
+            let expected = core.get_voxel(pos).unwrap();
+            let mut generated = Chunk::new(Chunk::get_chunk_pos(pos));
+            generated.generate_voxels(core.get_generator());
+            let base = generated.get_voxel(Chunk::get_in_chunk_pos(pos)).unwrap();
+            if base.value.to_bits() != expected.value.to_bits() {
+                edited += 1;
+            }
+
+            assert_eq!(
+                replayed.get_voxel(pos).unwrap().value.to_bits(),
+                expected.value.to_bits()
+            );
+        }
+    }
+    assert!(edited > 0);
+}
+
+#[test]
+fn edits_are_logged_before_save() {
+    let (mut core, dir) = temp_core("edit-log-unsaved");
+    core.set_edit_log(EditLog::open(dir.join(EDIT_LOG_FILE)).unwrap());
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 5, 9);
+    core.dig(hit, 2., 1.);
+    assert_eq!(EditLog::read(dir.join(EDIT_LOG_FILE)).unwrap().len(), 1);
+    core.dig(hit, 2., 1.);
+    assert_eq!(EditLog::read(dir.join(EDIT_LOG_FILE)).unwrap().len(), 2);
+}
+
+#[test]
+fn undone_edits_replay() {
+    let (mut core, dir) = temp_core("undo");
+    core.set_edit_log(EditLog::open(dir.join(EDIT_LOG_FILE)).unwrap());
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 5, 9);
+    core.dig(hit, 4., 1.);
+    assert!(core.undo());
+    core.dig(hit + Position::new(2, 0, 0), 3., 1.);
+    core.set_voxel(
+        hit + Position::new(0, 4, 0),
+        Voxel {
+            value: 0.5,
+            material: SNOW,
+        },
+    );
+    core.save().unwrap();
+
+    let records = EditLog::read(dir.join(EDIT_LOG_FILE)).unwrap();
+    assert_eq!(records.len(), 4);
+    let (mut replayed, _replayed_dir) = temp_core("undo-replay");
+    load_around(&mut replayed, Position::new(0, -1, 0));
+    replayed.replay_edits(&records);
+
+    for x in -6..=6 {
+        for y in -6..=6 {
+            for z in -6..=6 {
+                let pos = hit + Position::new(x, y, z);
+                let expected = core.get_voxel(pos).unwrap();
+                let voxel = replayed.get_voxel(pos).unwrap();
+                assert_eq!(voxel.value.to_bits(), expected.value.to_bits());
+                assert_eq!(voxel.material, expected.material);
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_generator_terrain_and_edits() {
+    let (mut core, _dir) = temp_core_with("flat", Box::new(FlatGenerator::new(4.5, SAND)));
+    load_around(&mut core, Position::new(0, 0, 0));
+
+    let surface = surface_below(&core, 3, -7);
+    assert_eq!(surface.y, 4);
+    assert_eq!(core.get_voxel(surface).unwrap().material, SAND);
+
+    core.dig(surface, 2., 1.);
+    let dug = core.get_voxel(surface).unwrap();
+    let chunk_pos = Chunk::get_chunk_pos(surface);
+    core.unload_chunk(chunk_pos);
+    core.load_chunk_now(chunk_pos);
+    assert_eq!(
+        core.get_voxel(surface).unwrap().value.to_bits(),
+        dug.value.to_bits()
+    );
+}
+
+#[test]
+fn fill_uses_targeted_material_until_one_is_picked() {
+    let (mut core, _dir) = temp_core_with("pick", Box::new(FlatGenerator::new(4.5, SAND)));
+    load_around(&mut core, Position::new(0, 0, 0));
+    core.player.inventory.add(SAND, 100.);
+    core.player.inventory.add(SNOW, 100.);
+    core.player.brush.material = SNOW;
+    core.player.brush.strength = 1.;
+
+    // looking straight down at the ground
+    core.player.set_pos(Vec3::new(8.2, 12., 8.3));
+    core.player.cam_state.target = Vec3::new(8.2, 0., 8.3);
+    let above = Position::new(8, 5, 8);
+    core.use_brush(1., true).unwrap();
+    assert!(core.get_voxel(above).unwrap().value >= 0.);
+    assert_eq!(core.get_voxel(above).unwrap().material, SAND);
+    assert!(core.player.inventory.get_amount(SAND) < 100.);
+
+    // looking up at the sky picks nothing
+    core.player.cam_state.target = Vec3::new(8.2, 20., 8.3);
+    assert_eq!(core.pick_material(), None);
+    assert!(!core.player.material_picked);
+
+    core.player.material_picked = true;
+    core.player.cam_state.target = Vec3::new(8.2, 0., 8.3);
+    let sand = core.player.inventory.get_amount(SAND);
+    core.use_brush(1., true).unwrap();
+    // sand is only given back, for the voxels turned into snow
+    assert!(core.player.inventory.get_amount(SAND) >= sand);
+    assert!(core.player.inventory.get_amount(SNOW) < 100.);
+}
+
+#[test]
+fn bedrock_is_reported_and_kept() {
+    let (mut core, _dir) = temp_core_with("bedrock", Box::new(FlatGenerator::new(4.5, BEDROCK)));
+    load_around(&mut core, Position::new(0, 0, 0));
+
+    let surface = surface_below(&core, 3, -7);
+    let before = core.get_voxel(surface).unwrap();
+    let report = core.dig(surface, 2., 1.);
+
+    assert_eq!(report.changed, 0);
+    assert!(report.resisted.contains(&surface));
+    assert_eq!(core.get_voxel(surface).unwrap().value, before.value);
+    assert_eq!(core.get_history().get_undo_count(), 0);
+}
+
+#[test]
+fn brush_edits_replay() {
+    let (mut core, dir) = temp_core("brush");
+    core.set_edit_log(EditLog::open(dir.join(EDIT_LOG_FILE)).unwrap());
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 5, 9);
+    let mut brush = Brush {
+        shape: BrushShape::Cylinder,
+        falloff: Falloff::Smooth,
+        mode: BrushMode::Flatten,
+        radius: 4.,
+        height: 3.,
+        strength: 0.5,
+        material: SNOW,
+    };
+    core.player.inventory.add(SNOW, 1000.);
+    assert!(core.apply_brush(&brush, hit).changed > 0);
+    brush.mode = BrushMode::Smooth;
+    brush.shape = BrushShape::Capsule;
+    core.apply_brush(&brush, hit + Position::new(2, 0, 0));
+    brush.mode = BrushMode::Paint;
+    brush.shape = BrushShape::Cube;
+    core.apply_brush(&brush, hit);
+    assert_eq!(core.get_voxel(hit).unwrap().material, SNOW);
+    core.save().unwrap();
+
+    let records = EditLog::read(dir.join(EDIT_LOG_FILE)).unwrap();
+    assert_eq!(records.len(), 3);
+    let (mut replayed, _replayed_dir) = temp_core("brush-replay");
+    load_around(&mut replayed, Position::new(0, -1, 0));
+    replayed.replay_edits(&records);
+
+    for x in -6..=6 {
+        for y in -6..=6 {
+            for z in -6..=6 {
+                let pos = hit + Position::new(x, y, z);
+                let expected = core.get_voxel(pos).unwrap();
+                let voxel = replayed.get_voxel(pos).unwrap();
+                assert_eq!(voxel.value.to_bits(), expected.value.to_bits());
+                assert_eq!(voxel.material, expected.material);
+            }
+        }
+    }
+}
+
+#[test]
+fn density_is_conserved_by_edits_and_undo() {
+    let (mut core, _dir) = temp_core("conserve");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 8, 8);
+    let total = world_density(&core, hit, 8);
+    let conserved = |core: &WorldCore| {
+        let now = world_density(core, hit, 8) + core.player.inventory.get_total() as f64;
+        (now - total).abs() < 1e-3
+    };
+
+    let report = core.dig(hit, 4., 1.);
+    assert!(report.changed > 0);
+    assert!(core.player.inventory.get_total() > 0.);
+    assert!(conserved(&core));
+
+    let (material, _) = core
+        .player
+        .inventory
+        .iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+    let voxel = Voxel {
+        value: 0.,
+        material,
+    };
+    assert!(core.fill(hit + Position::new(0, 2, 0), 3., voxel, 0.5) > 0);
+    assert!(conserved(&core));
+
+    assert!(core.undo());
+    assert!(conserved(&core));
+    assert!(core.redo());
+    assert!(conserved(&core));
+}
+
+#[test]
+fn undo_after_spending_leaves_inventory_in_debt() {
+    let (mut core, _dir) = temp_core("debt");
+    load_around(&mut core, Position::new(0, -1, 0));
+
+    let hit = surface_below(&core, 8, 8);
+    let material = core.get_voxel(hit).unwrap().material;
+    core.dig(hit, 3., 1.);
+    let dug = core.player.inventory.get_amount(material);
+    assert!(dug > 0.);
+
+    // the dug material is used up, then the dig is undone anyway
+    core.player.inventory.add(material, -dug);
+    assert!(core.undo());
+    assert!((core.player.inventory.get_amount(material) + dug).abs() < 1e-3);
+
+    // nothing is filled while in debt
+    let voxel = Voxel {
+        value: 0.,
+        material,
+    };
+    assert_eq!(core.fill(hit + Position::new(0, 2, 0), 3., voxel, 0.5), 0);
+
+    // digging again repays it
+    core.dig(hit, 3., 1.);
+    assert!(core.player.inventory.get_amount(material).abs() < 1e-3);
+}
+
+#[test]
+fn edited_terrain_graph_is_refused() {
+    let dir = TempDir::new("world-terrain-graph");
+    let store = RegionStore::new(&dir, SaveMode::Delta);
+    fs::create_dir_all(&dir).unwrap();
+    let graph_path = dir.join(TERRAIN_GRAPH_FILE);
+    fs::write(&graph_path, DEFAULT_TERRAIN_GRAPH).unwrap();
+
+    assert!(World::open_generator(&store).is_ok());
+    let terrain_hash = store.load_meta().unwrap().unwrap().terrain_hash;
+    assert_eq!(terrain_hash, Some(hash_source(DEFAULT_TERRAIN_GRAPH)));
+    assert!(World::open_generator(&store).is_ok());
+
+    let edited = DEFAULT_TERRAIN_GRAPH.replace("seed: 0", "seed: 1");
+    fs::write(&graph_path, edited).unwrap();
+    let err = World::open_generator(&store).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    fs::remove_file(&graph_path).unwrap();
+    assert!(World::open_generator(&store).is_err());
+}
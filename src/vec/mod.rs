@@ -39,6 +39,12 @@ where
     }
 }
 
+impl<T: Copy> From<[T; 3]> for Vec3<T> {
+    fn from(arr: [T; 3]) -> Self {
+        Self::new(arr[0], arr[1], arr[2])
+    }
+}
+
 impl<T> Into<Vec3<T>> for (T, T, T) {
     fn into(self) -> Vec3<T> {
         Vec3::new(self.0, self.1, self.2)
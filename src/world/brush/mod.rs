@@ -0,0 +1,351 @@
+use crate::{player::inventory::Inventory, utils::position::Position, vec::Vec3};
+
+use super::voxel::{
+    material::{self, MaterialRegistry},
+    EditOutcome, Voxel, VoxelId, EMPTY_VALUE,
+};
+
+const MIN_RADIUS: f32 = 1.;
+const MAX_RADIUS: f32 = 32.;
+const MIN_STRENGTH: f32 = 0.005;
+const MAX_STRENGTH: f32 = 1.;
+/// Strength change of one mouse wheel step.
+const STRENGTH_STEP: f32 = 1.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    Sphere,
+    Cube,
+    /// Vertical cylinder, `height` above and below the center.
+    Cylinder,
+    /// Vertical capsule, its middle segment goes `height` above and below
+    /// the center.
+    Capsule,
+}
+
+/// How the strength of a brush goes down from its center to its edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    /// Smoothstep, flat at the center and at the edge.
+    Smooth,
+    /// Quadratic, most of the strength is near the center.
+    Sharp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushMode {
+    /// Fills empty voxels with the material of the brush.
+    Add,
+    /// Digs solid voxels.
+    Subtract,
+    /// Moves voxels towards the mean of their neighbours.
+    Smooth,
+    /// Moves voxels towards a horizontal plane through the center.
+    Flatten,
+    /// Changes the material of solid voxels, leaving their value.
+    Paint,
+}
+
+impl BrushShape {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            BrushShape::Sphere => "sphere",
+            BrushShape::Cube => "cube",
+            BrushShape::Cylinder => "cylinder",
+            BrushShape::Capsule => "capsule",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sphere" => Some(BrushShape::Sphere),
+            "cube" => Some(BrushShape::Cube),
+            "cylinder" => Some(BrushShape::Cylinder),
+            "capsule" => Some(BrushShape::Capsule),
+            _ => None,
+        }
+    }
+
+    pub fn get_next(&self) -> Self {
+        match self {
+            BrushShape::Sphere => BrushShape::Cube,
+            BrushShape::Cube => BrushShape::Cylinder,
+            BrushShape::Cylinder => BrushShape::Capsule,
+            BrushShape::Capsule => BrushShape::Sphere,
+        }
+    }
+}
+
+impl Falloff {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Falloff::Constant => "constant",
+            Falloff::Linear => "linear",
+            Falloff::Smooth => "smooth",
+            Falloff::Sharp => "sharp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "constant" => Some(Falloff::Constant),
+            "linear" => Some(Falloff::Linear),
+            "smooth" => Some(Falloff::Smooth),
+            "sharp" => Some(Falloff::Sharp),
+            _ => None,
+        }
+    }
+
+    pub fn get_next(&self) -> Self {
+        match self {
+            Falloff::Constant => Falloff::Linear,
+            Falloff::Linear => Falloff::Smooth,
+            Falloff::Smooth => Falloff::Sharp,
+            Falloff::Sharp => Falloff::Constant,
+        }
+    }
+
+    /// Weight of the strength at `distance`, which goes from 0 at the center
+    /// to 1 at the edge.
+    pub fn get_weight(&self, distance: f32) -> f32 {
+        if !(0. ..1.).contains(&distance) {
+            return 0.;
+        }
+        match self {
+            Falloff::Constant => 1.,
+            Falloff::Linear => 1. - distance,
+            Falloff::Smooth => 1. - distance * distance * (3. - 2. * distance),
+            Falloff::Sharp => (1. - distance) * (1. - distance),
+        }
+    }
+}
+
+impl BrushMode {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            BrushMode::Add => "fill",
+            BrushMode::Subtract => "dig",
+            BrushMode::Smooth => "smooth",
+            BrushMode::Flatten => "flatten",
+            BrushMode::Paint => "paint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fill" => Some(BrushMode::Add),
+            "dig" => Some(BrushMode::Subtract),
+            "smooth" => Some(BrushMode::Smooth),
+            "flatten" => Some(BrushMode::Flatten),
+            "paint" => Some(BrushMode::Paint),
+            _ => None,
+        }
+    }
+
+    pub fn get_next(&self) -> Self {
+        match self {
+            BrushMode::Subtract => BrushMode::Add,
+            BrushMode::Add => BrushMode::Smooth,
+            BrushMode::Smooth => BrushMode::Flatten,
+            BrushMode::Flatten => BrushMode::Paint,
+            BrushMode::Paint => BrushMode::Subtract,
+        }
+    }
+
+    /// Mode undoing this one: adding and subtracting swap, the others stay.
+    pub fn get_inverse(&self) -> Self {
+        match self {
+            BrushMode::Add => BrushMode::Subtract,
+            BrushMode::Subtract => BrushMode::Add,
+            mode => *mode,
+        }
+    }
+}
+
+/// Tool editing the voxels around a center, see
+/// [`WorldCore::apply_brush`](super::core::WorldCore::apply_brush).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub falloff: Falloff,
+    pub mode: BrushMode,
+    pub radius: f32,
+    /// Half height of the straight part of cylinders and capsules.
+    pub height: f32,
+    /// Largest change of a voxel value, at the center.
+    pub strength: f32,
+    /// Material added or painted.
+    pub material: VoxelId,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            shape: BrushShape::Sphere,
+            falloff: Falloff::Linear,
+            mode: BrushMode::Subtract,
+            radius: 6.,
+            height: 3.,
+            strength: 0.05,
+            material: material::ROCK,
+        }
+    }
+}
+
+impl Brush {
+    /// Sphere with a linear falloff.
+    pub fn sphere(mode: BrushMode, radius: f32, strength: f32) -> Self {
+        Self {
+            mode,
+            radius,
+            strength,
+            ..Self::default()
+        }
+    }
+
+    /// Changes the radius by `steps` voxels.
+    pub fn resize(&mut self, steps: f32) {
+        self.radius = (self.radius + steps).clamp(MIN_RADIUS, MAX_RADIUS);
+    }
+
+    /// Scales the strength by `STRENGTH_STEP` per step.
+    pub fn scale_strength(&mut self, steps: f32) {
+        self.strength =
+            (self.strength * STRENGTH_STEP.powf(steps)).clamp(MIN_STRENGTH, MAX_STRENGTH);
+    }
+
+    /// Half size of the box holding the brush.
+    pub fn get_extent(&self) -> Position {
+        let radius = self.radius.ceil() as i64;
+        let half_height = match self.shape {
+            BrushShape::Sphere | BrushShape::Cube => self.radius,
+            BrushShape::Cylinder => self.height,
+            BrushShape::Capsule => self.height + self.radius,
+        };
+        Position::new(radius, half_height.ceil() as i64, radius)
+    }
+
+    /// Distance of `offset` from the center, scaled so the edge of the shape
+    /// is at 1.
+    pub fn get_distance(&self, offset: Position) -> f32 {
+        let v = Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32);
+        match self.shape {
+            BrushShape::Sphere => v.length() / self.radius,
+            BrushShape::Cube => v.x.abs().max(v.y.abs()).max(v.z.abs()) / self.radius,
+            BrushShape::Cylinder => {
+                let across = (v.x * v.x + v.z * v.z).sqrt() / self.radius;
+                across.max(v.y.abs() / self.height)
+            }
+            BrushShape::Capsule => {
+                let y = (v.y.abs() - self.height).max(0.);
+                (v.x * v.x + y * y + v.z * v.z).sqrt() / self.radius
+            }
+        }
+    }
+
+    /// Share of the strength applied at `offset` from the center.
+    pub fn get_weight(&self, offset: Position) -> f32 {
+        self.falloff.get_weight(self.get_distance(offset))
+    }
+
+    /// Applies the brush to `voxel`, at `offset` from the center. `average` is
+    /// the mean value of the neighbours of the voxel, only used to smooth.
+    ///
+    /// Density added is taken from `inventory` and density removed goes to it.
+    /// Painting trades the density of the old material for the same amount of
+    /// the new one, and needs enough of it in stock.
+    pub fn apply(
+        &self,
+        voxel: &mut Voxel,
+        offset: Position,
+        average: f32,
+        materials: &MaterialRegistry,
+        inventory: &mut Inventory,
+    ) -> EditOutcome {
+        let weight = self.get_weight(offset);
+        if weight <= 0. {
+            return EditOutcome::Unchanged;
+        }
+        let strength = self.strength * weight;
+        let before = *voxel;
+
+        let outcome = match self.mode {
+            BrushMode::Add => {
+                // the density already in a voxel of another material is
+                // bought with the new one too
+                let held = match voxel.material == self.material {
+                    true => 0.,
+                    false => voxel.get_density(),
+                };
+                let stock = inventory.get_amount(self.material) - held;
+                if stock > 0. && voxel.fill(self.material, strength.min(stock)) {
+                    EditOutcome::Changed
+                } else {
+                    EditOutcome::Unchanged
+                }
+            }
+            BrushMode::Subtract => voxel.dig_material(strength, materials),
+            BrushMode::Smooth => move_towards(voxel, average, strength, materials, inventory),
+            BrushMode::Flatten => {
+                let plane = (-offset.y as f32).clamp(EMPTY_VALUE, 1.);
+                move_towards(voxel, plane, strength, materials, inventory)
+            }
+            BrushMode::Paint => {
+                let stock = inventory.get_amount(self.material);
+                if voxel.value >= 0.
+                    && voxel.material != self.material
+                    && stock >= voxel.get_density()
+                {
+                    voxel.material = self.material;
+                    EditOutcome::Changed
+                } else {
+                    EditOutcome::Unchanged
+                }
+            }
+        };
+
+        if outcome == EditOutcome::Changed {
+            inventory.exchange(before, *voxel);
+        }
+        outcome
+    }
+}
+
+/// Moves the value of `voxel` towards `target` by at most `strength`, keeping
+/// its material. Lowering is slowed down by the hardness of the material and
+/// raising stops when `inventory` runs out of it.
+fn move_towards(
+    voxel: &mut Voxel,
+    target: f32,
+    strength: f32,
+    materials: &MaterialRegistry,
+    inventory: &Inventory,
+) -> EditOutcome {
+    let delta = (target - voxel.value).clamp(-strength, strength);
+
+    if delta < 0. {
+        if voxel.value <= EMPTY_VALUE {
+            return EditOutcome::Unchanged;
+        }
+        let hardness = match materials.get(voxel.material) {
+            Some(material) if !material.diggable => {
+                return match voxel.value >= 0. {
+                    true => EditOutcome::Resisted,
+                    false => EditOutcome::Unchanged,
+                };
+            }
+            Some(material) => material.hardness,
+            None => 1.,
+        };
+        voxel.value = (voxel.value + delta / hardness).max(EMPTY_VALUE);
+    } else {
+        let delta = delta.min(inventory.get_amount(voxel.material));
+        if delta <= 0. {
+            return EditOutcome::Unchanged;
+        }
+        voxel.value = (voxel.value + delta).min(1.);
+    }
+    EditOutcome::Changed
+}
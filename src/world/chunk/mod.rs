@@ -1,71 +1,44 @@
-use std::sync::Arc;
-
-use wgpu::{Device, RenderPass};
+use std::mem;
 
 use crate::{
-    app_state::game_state::graphics::{mesh::Mesh, vertex::Vertex},
     utils::{direction::Direction, position::Position, true_mod::true_mod},
     vec::Vec3,
 };
 
 use super::{
-    generator::Generator,
-    voxel::{voxel_data::VoxelData, voxels_to_vertex::append_vertex, Voxel},
+    generator::TerrainGenerator,
+    region::RegionStore,
+    voxel::{voxel_data::VoxelData, Voxel},
 };
 
+pub mod neighbourhood;
+
 pub const CHUNK_REAL_SIZE: usize = 16;
 pub const CHUNK_VOXELS_SIZE: usize = CHUNK_REAL_SIZE + 1;
 pub const CHUNK_VOXELS_VOLUME: usize = CHUNK_VOXELS_SIZE * CHUNK_VOXELS_SIZE * CHUNK_VOXELS_SIZE;
 
 pub struct Chunk {
     pos: Position,
-    mesh: Option<Mesh>,
-    voxels: [Voxel; CHUNK_VOXELS_VOLUME],
+    voxels: Vec<Voxel>,
+    modified: bool,
 }
 
 impl Chunk {
     pub fn new(pos: Position) -> Self {
         Self {
             pos: pos,
-            voxels: [Voxel {
-                value: 0.,
-                color: [0.; 3],
-            }; CHUNK_VOXELS_VOLUME],
-            mesh: None,
-        }
-    }
-
-    pub fn update_mesh(&mut self, device: &Arc<Device>) {
-        let mut vertex: Vec<Vertex> = Vec::new();
-        for x in 0..CHUNK_REAL_SIZE {
-            for y in 0..CHUNK_REAL_SIZE {
-                for z in 0..CHUNK_REAL_SIZE {
-                    append_vertex(
-                        Position::new(x as i64, y as i64, z as i64),
-                        self,
-                        &mut vertex,
-                    );
-                }
-            }
+            voxels: vec![
+                Voxel {
+                    value: 0.,
+                    material: 0,
+                };
+                CHUNK_VOXELS_VOLUME
+            ],
+            modified: false,
         }
-
-        for v in vertex.iter_mut() {
-            v.position = [
-                v.position[0] + (self.pos.x * CHUNK_REAL_SIZE as i64) as f32,
-                v.position[1] + (self.pos.y * CHUNK_REAL_SIZE as i64) as f32,
-                v.position[2] + (self.pos.z * CHUNK_REAL_SIZE as i64) as f32,
-            ]
-        }
-
-        match &self.mesh {
-            Some(mesh) => mesh.destroy(),
-            _ => {}
-        }
-
-        self.mesh = Some(Mesh::new(vertex, device));
     }
 
-    pub fn generate_voxels(&mut self, generator: &Generator) {
+    pub fn generate_voxels(&mut self, generator: &dyn TerrainGenerator) {
         let offset = Vec3::new(
             (self.pos.x * CHUNK_REAL_SIZE as i64) as f64,
             (self.pos.y * CHUNK_REAL_SIZE as i64) as f64,
@@ -75,9 +48,38 @@ impl Chunk {
         generator.generate_voxels(offset, &mut self.voxels, CHUNK_VOXELS_SIZE)
     }
 
-    pub fn generate(&mut self, generator: &Generator, device: &Arc<Device>) {
-        self.generate_voxels(generator);
-        self.update_mesh(device);
+    /// Same as [`Chunk::generate_voxels`], but prefers voxels previously saved to `store`.
+    pub fn load(&mut self, store: &RegionStore, generator: &dyn TerrainGenerator) {
+        match store.load_chunk(self, generator) {
+            Ok(true) => {}
+            Ok(false) => self.generate_voxels(generator),
+            Err(err) => {
+                log::error!("failed to load chunk {:?}: {}", self.pos, err);
+                self.generate_voxels(generator);
+            }
+        }
+    }
+
+    pub fn get_voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    pub fn set_voxels(&mut self, voxels: &[Voxel]) {
+        self.voxels.copy_from_slice(voxels);
+    }
+
+    /// Bytes taken by the voxels of the chunk.
+    pub fn get_voxel_bytes(&self) -> usize {
+        self.voxels.capacity() * mem::size_of::<Voxel>()
+    }
+
+    /// Whether the chunk was edited since it was generated or loaded.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn set_modified(&mut self, modified: bool) {
+        self.modified = modified;
     }
 
     pub fn pos_to_index(pos: Position) -> Option<usize> {
@@ -92,10 +94,16 @@ impl Chunk {
     }
 
     fn cord_to_chunk_cord(v: i64) -> i64 {
-        if v < 0 {
-            return v / CHUNK_REAL_SIZE as i64 - 1;
+        v.div_euclid(CHUNK_REAL_SIZE as i64)
+    }
+
+    /// Chunk cords along one axis whose voxels include the global cord `v`.
+    fn cord_to_owning_chunk_cords(v: i64) -> Vec<i64> {
+        let chunk_cord = Self::cord_to_chunk_cord(v);
+        if true_mod(v, CHUNK_REAL_SIZE as i64) == 0 {
+            vec![chunk_cord - 1, chunk_cord]
         } else {
-            return v / CHUNK_REAL_SIZE as i64;
+            vec![chunk_cord]
         }
     }
 
@@ -106,6 +114,21 @@ impl Chunk {
             Self::cord_to_chunk_cord(pos.z),
         )
     }
+
+    /// Every chunk storing the voxel at global `pos`. Chunks overlap by one
+    /// voxel, so positions on a chunk border belong to up to 8 chunks.
+    pub fn get_owning_chunks(pos: Position) -> Vec<Position> {
+        let mut chunks = vec![];
+        for z in Self::cord_to_owning_chunk_cords(pos.z) {
+            for y in Self::cord_to_owning_chunk_cords(pos.y) {
+                for x in Self::cord_to_owning_chunk_cords(pos.x) {
+                    chunks.push(Position::new(x, y, z));
+                }
+            }
+        }
+        chunks
+    }
+
     pub fn get_in_chunk_pos(pos: Position) -> Position {
         Position::new(
             true_mod(pos.x, CHUNK_REAL_SIZE as i64),
@@ -134,6 +157,7 @@ impl Chunk {
         match Self::pos_to_index(in_chunk_position) {
             Some(index) => {
                 self.voxels[index] = voxel;
+                self.modified = true;
                 return true;
             }
             _ => {
@@ -142,47 +166,6 @@ impl Chunk {
         }
     }
 
-    pub fn fill(&mut self, center: Position, radius: f32, voxel: Voxel, value: f32) -> usize {
-        let mut count: usize = 0;
-
-        for i in 0..CHUNK_VOXELS_VOLUME {
-            let pos = Self::index_to_pos(i) + self.pos.mul_scalar(CHUNK_REAL_SIZE as i64) - center;
-
-            let vec = Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
-            let l = vec.length();
-
-            if l < radius && self.voxels[i].value < 0. {
-                count += 1;
-                self.voxels[i].value = self.voxels[i].value.max(-0.1);
-                self.voxels[i].value = self.voxels[i].value + value * ((radius - l) / radius);
-                self.voxels[i].value = self.voxels[i].value.min(1.0);
-
-                self.voxels[i].color = voxel.color;
-            }
-        }
-
-        return count;
-    }
-
-    pub fn dig(&mut self, center: Position, radius: f32, value: f32) -> usize {
-        let mut count: usize = 0;
-
-        for i in 0..CHUNK_VOXELS_VOLUME {
-            let pos = Self::index_to_pos(i) + self.pos.mul_scalar(CHUNK_REAL_SIZE as i64) - center;
-
-            let vec = Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
-            let l = vec.length();
-
-            if l < radius && self.voxels[i].value >= 0. {
-                count += 1;
-                self.voxels[i].value -= value * (radius - l) / radius;
-                self.voxels[i].value = self.voxels[i].value.max(-0.1);
-            }
-        }
-
-        return count;
-    }
-
     pub fn get_voxel(&self, in_chunk_position: Position) -> Option<Voxel> {
         match Self::pos_to_index(in_chunk_position.clone()) {
             Some(index) => {
@@ -250,15 +233,6 @@ impl Chunk {
         }
     }
 
-    pub fn update(&self) {}
-
-    pub fn draw<'a>(self: &'a Self, render_pass: &mut RenderPass<'a>) {
-        match self.mesh.as_ref() {
-            Some(mesh) => mesh.draw(render_pass),
-            _ => {}
-        }
-    }
-
     pub fn get_position(&self) -> Position {
         return self.pos;
     }
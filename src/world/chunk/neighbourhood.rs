@@ -0,0 +1,95 @@
+use std::cell::Cell;
+
+use crate::{utils::position::Position, world::voxel::Voxel};
+
+use super::{Chunk, CHUNK_REAL_SIZE};
+
+const NEIGHBOURHOOD_SIZE: usize = 27;
+
+/// Read-only view of a chunk together with its 26 neighbours, addressed in
+/// voxel coordinates relative to the center chunk.
+pub struct ChunkNeighbourhood<'a> {
+    center: &'a Chunk,
+    chunks: [Option<&'a Chunk>; NEIGHBOURHOOD_SIZE],
+    /// Neighbours that were asked for a voxel while not loaded.
+    missing_sampled: Cell<u32>,
+}
+
+impl<'a> ChunkNeighbourhood<'a> {
+    pub fn new<F>(center: &'a Chunk, get_chunk: F) -> Self
+    where
+        F: Fn(Position) -> Option<&'a Chunk>,
+    {
+        let mut chunks = [None; NEIGHBOURHOOD_SIZE];
+        for pos in center.get_position().iter_neighbors(true) {
+            let offset = pos - center.get_position();
+            chunks[Self::offset_to_index(offset)] = get_chunk(pos);
+        }
+        chunks[Self::offset_to_index(Position::new(0, 0, 0))] = Some(center);
+
+        Self {
+            center,
+            chunks,
+            missing_sampled: Cell::new(0),
+        }
+    }
+
+    /// View without any loaded neighbours.
+    pub fn alone(center: &'a Chunk) -> Self {
+        Self::new(center, |_| None)
+    }
+
+    pub fn offset_to_index(offset: Position) -> usize {
+        ((offset.x + 1) + (offset.y + 1) * 3 + (offset.z + 1) * 9) as usize
+    }
+
+    pub fn index_to_offset(index: usize) -> Position {
+        let index = index as i64;
+        Position::new(index % 3 - 1, (index / 3) % 3 - 1, index / 9 - 1)
+    }
+
+    pub fn get_center(&self) -> &'a Chunk {
+        self.center
+    }
+
+    fn get_chunk_offset(v: i64) -> i64 {
+        if (0..=CHUNK_REAL_SIZE as i64).contains(&v) {
+            0
+        } else {
+            v.div_euclid(CHUNK_REAL_SIZE as i64)
+        }
+    }
+
+    /// Voxel at `pos` relative to the center chunk, `None` if it lies in a
+    /// neighbour that is not loaded or outside the neighbourhood.
+    pub fn get_voxel(&self, pos: Position) -> Option<Voxel> {
+        let offset = Position::new(
+            Self::get_chunk_offset(pos.x),
+            Self::get_chunk_offset(pos.y),
+            Self::get_chunk_offset(pos.z),
+        );
+        if offset.x.abs() > 1 || offset.y.abs() > 1 || offset.z.abs() > 1 {
+            return None;
+        }
+
+        let index = Self::offset_to_index(offset);
+        match self.chunks[index] {
+            Some(chunk) => chunk.get_voxel(pos - offset.mul_scalar(CHUNK_REAL_SIZE as i64)),
+            None => {
+                self.missing_sampled
+                    .set(self.missing_sampled.get() | 1 << index);
+                None
+            }
+        }
+    }
+
+    /// Bit mask (see [`ChunkNeighbourhood::offset_to_index`]) of the missing
+    /// neighbours that were sampled so far.
+    pub fn get_missing_sampled(&self) -> u32 {
+        self.missing_sampled.get()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.chunks.iter().all(|chunk| chunk.is_some())
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeSet;
+
+use crate::{utils::position::Position, vec::Vec3, world::chunk::CHUNK_REAL_SIZE};
+
+/// How much slower chunks behind the player load than the ones in front of
+/// them, a chunk straight behind counts as `1 + VIEW_WEIGHT` times as far.
+const VIEW_WEIGHT: f32 = 2.;
+/// Like `VIEW_WEIGHT` for the direction the player moves in, at full speed.
+const MOTION_WEIGHT: f32 = 4.;
+/// Speed, in voxels per second, from which `MOTION_WEIGHT` fully applies.
+const FULL_MOTION_SPEED: f32 = 20.;
+/// Cosine of the angle the player has to turn by before the queue is sorted
+/// again.
+const TURN_THRESHOLD: f32 = 0.97;
+/// Velocity change, in voxels per second, before the queue is sorted again.
+const VELOCITY_THRESHOLD: f32 = 2.;
+/// Chunks around the predicted path that are prefetched.
+const PATH_RADIUS: i64 = 1;
+/// Chunks farther than the render distance that are kept loaded, so chunks on
+/// the edge don't load and unload in turn.
+const RETENTION_MARGIN: f32 = 1.;
+
+/// Ellipsoid of chunks around the player, with a horizontal and a vertical
+/// radius in chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoadRange {
+    pub horizontal: usize,
+    pub vertical: usize,
+}
+
+impl LoadRange {
+    pub fn new(horizontal: usize, vertical: usize) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// Whether the chunk `delta` away from the center is in range, grown by
+    /// `margin` chunks. Radii count from the center of the middle chunk, so a
+    /// radius of 0 is that chunk alone.
+    pub fn contains(&self, delta: Position, margin: f32) -> bool {
+        let horizontal = self.horizontal as f32 + 0.5 + margin;
+        let vertical = self.vertical as f32 + 0.5 + margin;
+        let (x, y, z) = (delta.x as f32, delta.y as f32, delta.z as f32);
+
+        (x * x + z * z) / (horizontal * horizontal) + y * y / (vertical * vertical) <= 1.
+    }
+
+    /// Every chunk in range around `center`.
+    pub fn iter_around(&self, center: Position) -> impl Iterator<Item = Position> + '_ {
+        let (h, v) = (self.horizontal as i64, self.vertical as i64);
+        (-h..=h).flat_map(move |x| {
+            (-v..=v).flat_map(move |y| {
+                (-h..=h).filter_map(move |z| {
+                    let delta = Position::new(x, y, z);
+                    self.contains(delta, 0.).then(|| center + delta)
+                })
+            })
+        })
+    }
+}
+
+/// Where the player is, where they look and where they go.
+#[derive(Clone, Copy, Debug)]
+pub struct LoadFocus {
+    pub eye: Vec3<f32>,
+    pub look_dir: Vec3<f32>,
+    /// Voxels per second.
+    pub velocity: Vec3<f32>,
+}
+
+impl Default for LoadFocus {
+    fn default() -> Self {
+        Self {
+            eye: Vec3::new(0., 0., 0.),
+            look_dir: Vec3::new(0., 0., -1.),
+            velocity: Vec3::new(0., 0., 0.),
+        }
+    }
+}
+
+/// Chunks around the player that may need loading, nearest first, the ones
+/// in view and ahead before the ones behind. Chunks along the path the player
+/// is predicted to take are added too, so they are loaded before the player
+/// gets there.
+///
+/// It is refilled when the player changes chunk or path and sorted again
+/// when they turn or change speed, see [`LoadQueue::update`].
+pub struct LoadQueue {
+    /// Sorted by decreasing priority score, so the next chunk is at the end.
+    positions: Vec<Position>,
+    center: Option<Position>,
+    range: LoadRange,
+    /// Chunks the player goes through until the end of the prefetch time.
+    path: Vec<Position>,
+    focus: LoadFocus,
+}
+
+impl LoadQueue {
+    pub fn new() -> Self {
+        Self {
+            positions: vec![],
+            center: None,
+            range: LoadRange::new(0, 0),
+            path: vec![],
+            focus: LoadFocus::default(),
+        }
+    }
+
+    /// Score of the chunk at `chunk_pos`, lower loads first. It is the
+    /// distance from the eye to the center of the chunk, scaled up with the
+    /// angle to the look direction and, when moving, to the velocity.
+    pub fn get_priority(chunk_pos: Position, focus: &LoadFocus) -> f32 {
+        let size = CHUNK_REAL_SIZE as f32;
+        let center = Vec3::new(
+            (chunk_pos.x as f32 + 0.5) * size,
+            (chunk_pos.y as f32 + 0.5) * size,
+            (chunk_pos.z as f32 + 0.5) * size,
+        );
+        let to_chunk = center - focus.eye;
+        let distance = to_chunk.length();
+        if distance == 0. {
+            return 0.;
+        }
+
+        let look_cos = to_chunk.dot(focus.look_dir) / (distance * focus.look_dir.length());
+        let mut priority = distance * (1. + VIEW_WEIGHT * (1. - look_cos) / 2.);
+
+        let speed = focus.velocity.length();
+        if speed > 0. {
+            let motion_cos = to_chunk.dot(focus.velocity) / (distance * speed);
+            let motion = (speed / FULL_MOTION_SPEED).min(1.);
+            priority *= 1. + MOTION_WEIGHT * motion * (1. - motion_cos) / 2.;
+        }
+        priority
+    }
+
+    /// Chunks the player goes through in `prefetch_time` seconds, starting
+    /// with `center`.
+    pub fn get_path(center: Position, focus: &LoadFocus, prefetch_time: f32) -> Vec<Position> {
+        let ahead = focus.velocity * prefetch_time;
+        let size = CHUNK_REAL_SIZE as f32;
+        // half a chunk per step, so no chunk is skipped
+        let steps = (ahead.length() / size * 2.).ceil() as usize;
+
+        let mut path = vec![center];
+        for step in 1..=steps {
+            let pos = focus.eye + ahead * (step as f32 / steps as f32);
+            let chunk_pos = Position::new(
+                (pos.x / size).floor() as i64,
+                (pos.y / size).floor() as i64,
+                (pos.z / size).floor() as i64,
+            );
+            if path.last() != Some(&chunk_pos) {
+                path.push(chunk_pos);
+            }
+        }
+        path
+    }
+
+    /// Refills the queue when `center`, the range or the predicted path
+    /// changed: every chunk in `range` around `center` and the chunks around
+    /// the path. Sorts it again when the look direction turned
+    /// or the velocity changed. Returns whether it was refilled.
+    pub fn update(
+        &mut self,
+        center: Position,
+        range: LoadRange,
+        focus: LoadFocus,
+        prefetch_time: f32,
+    ) -> bool {
+        let path = Self::get_path(center, &focus, prefetch_time);
+        let refill = self.center != Some(center) || self.range != range || self.path != path;
+        let turned = focus.look_dir.cos(self.focus.look_dir) < TURN_THRESHOLD;
+        let accelerated = (focus.velocity - self.focus.velocity).length() > VELOCITY_THRESHOLD;
+        if !refill && !turned && !accelerated {
+            return false;
+        }
+
+        self.focus = focus;
+        if refill {
+            self.center = Some(center);
+            self.range = range;
+            self.path = path;
+
+            let mut positions: BTreeSet<Position> = range.iter_around(center).collect();
+            // the path starts at the center, already covered by the range
+            for pos in self.path.iter().skip(1) {
+                Self::add_cube(&mut positions, *pos, PATH_RADIUS);
+            }
+            self.positions = positions.into_iter().collect();
+        }
+
+        // scores are positive, so their bits sort like them
+        self.positions.sort_by_cached_key(|pos| {
+            let priority = Self::get_priority(*pos, &focus);
+            std::cmp::Reverse(priority.to_bits())
+        });
+        refill
+    }
+
+    fn add_cube(positions: &mut BTreeSet<Position>, center: Position, radius: i64) {
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                for z in -radius..=radius {
+                    positions.insert(center + Position::new(x, y, z));
+                }
+            }
+        }
+    }
+
+    /// Whether the chunk at `pos` is kept loaded: it is at most
+    /// `RETENTION_MARGIN` farther than the queued ones. Everything is in
+    /// range before the first update.
+    pub fn is_in_range(&self, pos: Position) -> bool {
+        let center = match self.center {
+            Some(center) => center,
+            None => return true,
+        };
+        if self.range.contains(pos - center, RETENTION_MARGIN) {
+            return true;
+        }
+
+        let max = PATH_RADIUS + RETENTION_MARGIN as i64;
+        self.path.iter().skip(1).any(|path_pos| {
+            let delta = pos - *path_pos;
+            delta.x.abs().max(delta.y.abs()).max(delta.z.abs()) <= max
+        })
+    }
+
+    /// Refills the queue on the next update, for chunks it dropped that are
+    /// needed again.
+    pub fn invalidate(&mut self) {
+        self.center = None;
+    }
+
+    /// Takes the chunk with the best priority that `is_needed` accepts.
+    /// Chunks skipped on the way are dropped until the next refill.
+    pub fn pop<F>(&mut self, is_needed: F) -> Option<Position>
+    where
+        F: Fn(Position) -> bool,
+    {
+        while let Some(pos) = self.positions.pop() {
+            if is_needed(pos) {
+                return Some(pos);
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+impl Default for LoadQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    utils::position::Position,
+    world::{chunk::Chunk, generator::TerrainGenerator, region::RegionStore},
+};
+
+/// Position of a chunk to load, with the ticket it was requested with.
+type Job = (Position, u64);
+
+#[derive(Default)]
+struct Jobs {
+    queue: VecDeque<Job>,
+    shutdown: bool,
+}
+
+/// Jobs shared with the workers, they sleep on `available` while it is empty.
+#[derive(Default)]
+struct JobQueue {
+    jobs: Mutex<Jobs>,
+    available: Condvar,
+}
+
+/// Loads chunks on a pool of worker threads that live as long as it does.
+///
+/// Positions are queued with [`ChunkLoader::request`] and the loaded chunks
+/// are picked up with [`ChunkLoader::collect`], which never waits for them.
+pub struct ChunkLoader {
+    queue: Arc<JobQueue>,
+    results: Receiver<(Chunk, u64)>,
+    /// Tickets of the requested chunks that were not collected yet, queued or
+    /// being loaded. A chunk coming back with another ticket is from a
+    /// cancelled job.
+    pending: BTreeMap<Position, u64>,
+    next_ticket: u64,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ChunkLoader {
+    pub fn new(
+        generator: Arc<dyn TerrainGenerator>,
+        store: Arc<RegionStore>,
+        worker_count: usize,
+    ) -> Self {
+        let queue = Arc::new(JobQueue::default());
+        let (sender, results) = mpsc::channel();
+
+        let workers = (0..worker_count.max(1))
+            .map(|i| {
+                let queue = queue.clone();
+                let sender = sender.clone();
+                let generator = generator.clone();
+                let store = store.clone();
+
+                thread::Builder::new()
+                    .name(format!("chunk-loader-{}", i))
+                    .spawn(move || run_worker(&queue, &sender, generator.as_ref(), &store))
+                    .expect("failed to spawn chunk loader thread")
+            })
+            .collect();
+
+        Self {
+            queue,
+            results,
+            pending: BTreeMap::new(),
+            next_ticket: 0,
+            workers,
+        }
+    }
+
+    /// One worker per core, leaving one for the render thread.
+    pub fn get_default_worker_count() -> usize {
+        thread::available_parallelism().map_or(4, |count| count.get().saturating_sub(1).max(1))
+    }
+
+    pub fn is_pending(&self, pos: Position) -> bool {
+        self.pending.contains_key(&pos)
+    }
+
+    pub fn get_pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queues the chunk at `pos`, unless it is already pending.
+    pub fn request(&mut self, pos: Position) {
+        if self.pending.contains_key(&pos) {
+            return;
+        }
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.pending.insert(pos, ticket);
+
+        self.queue
+            .jobs
+            .lock()
+            .unwrap()
+            .queue
+            .push_back((pos, ticket));
+        self.queue.available.notify_one();
+    }
+
+    /// Drops the jobs of the positions `keep` rejects. Jobs already being
+    /// loaded finish, but their chunk is thrown away by
+    /// [`ChunkLoader::collect`]. Returns the number of cancelled jobs.
+    pub fn cancel<F>(&mut self, keep: F) -> usize
+    where
+        F: Fn(Position) -> bool,
+    {
+        let before = self.pending.len();
+        self.pending.retain(|pos, _| keep(*pos));
+        self.queue
+            .jobs
+            .lock()
+            .unwrap()
+            .queue
+            .retain(|(pos, _)| keep(*pos));
+        before - self.pending.len()
+    }
+
+    /// Forgets the job of `pos`, when the chunk was loaded some other way.
+    pub fn forget(&mut self, pos: Position) {
+        if self.pending.remove(&pos).is_some() {
+            self.queue
+                .jobs
+                .lock()
+                .unwrap()
+                .queue
+                .retain(|(job, _)| *job != pos);
+        }
+    }
+
+    /// Chunks loaded since the last call, without waiting for the others.
+    pub fn collect(&mut self) -> Vec<Chunk> {
+        let mut chunks = vec![];
+        while let Ok((chunk, ticket)) = self.results.try_recv() {
+            let pos = chunk.get_position();
+            if self.pending.get(&pos) == Some(&ticket) {
+                self.pending.remove(&pos);
+                chunks.push(chunk);
+            }
+        }
+        chunks
+    }
+}
+
+impl Drop for ChunkLoader {
+    fn drop(&mut self) {
+        self.queue.jobs.lock().unwrap().shutdown = true;
+        self.queue.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(
+    queue: &JobQueue,
+    sender: &Sender<(Chunk, u64)>,
+    generator: &dyn TerrainGenerator,
+    store: &RegionStore,
+) {
+    loop {
+        let (pos, ticket) = {
+            let mut jobs = queue.jobs.lock().unwrap();
+            loop {
+                if jobs.shutdown {
+                    return;
+                }
+                if let Some(job) = jobs.queue.pop_front() {
+                    break job;
+                }
+                jobs = queue.available.wait(jobs).unwrap();
+            }
+        };
+
+        let mut chunk = Chunk::new(pos);
+        chunk.load(store, generator);
+        if sender.send((chunk, ticket)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,961 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io, mem,
+    sync::Arc,
+};
+
+use chrono::Utc;
+use strum::IntoEnumIterator;
+
+use crate::{
+    player::Player,
+    utils::{direction::Direction, position::Position},
+    vec::Vec3,
+};
+
+use self::{
+    load_queue::{LoadFocus, LoadQueue, LoadRange},
+    loader::ChunkLoader,
+};
+
+use super::{
+    brush::{Brush, BrushMode},
+    chunk::{neighbourhood::ChunkNeighbourhood, Chunk, CHUNK_REAL_SIZE},
+    edit_log::{Edit, EditLog, EditRecord},
+    generator::{biome::Biome, TerrainGenerator},
+    history::EditHistory,
+    raycast::{self, RayCast},
+    region::RegionStore,
+    voxel::{material::MaterialRegistry, EditOutcome, EditReport, Voxel, VoxelId},
+};
+
+pub mod load_queue;
+pub mod loader;
+
+/// Chunks queued on the loader per worker thread.
+const JOBS_PER_WORKER: usize = 2;
+/// Distance the player can move in one update before it counts as a
+/// teleport instead of a velocity.
+const TELEPORT_DISTANCE: f32 = 64.;
+/// Time constant, in seconds, of the smoothing of the player velocity.
+const VELOCITY_SMOOTHING: f32 = 0.2;
+/// Bytes of voxels and meshes kept loaded before chunks are evicted.
+const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+/// Cosine of the angle from the look direction within which chunks are in
+/// view, a bit wider than the field of view so turning doesn't evict them.
+const VIEW_COS: f32 = 0.5;
+
+/// How well chunk loading keeps up with the player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadStats {
+    /// Chunks the player moved into.
+    pub chunks_entered: usize,
+    /// Chunks the player moved into before they were loaded.
+    pub chunks_missed: usize,
+}
+
+/// Memory taken by the loaded chunks, see [`WorldCore::set_memory_budget`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub resident_chunks: usize,
+    pub voxel_bytes: usize,
+    /// Bytes of the meshes reported with [`WorldCore::set_mesh_bytes`].
+    pub mesh_bytes: usize,
+    /// Chunks unloaded to stay within the budget.
+    pub evicted_chunks: usize,
+    /// Evicted chunks that were modified, and saved before being unloaded.
+    pub evicted_modified_chunks: usize,
+}
+
+/// When a loaded chunk was last in view and how big its mesh is.
+#[derive(Clone, Copy, Debug, Default)]
+struct ChunkUsage {
+    last_viewed: u64,
+    mesh_bytes: usize,
+}
+
+/// Simulation side of the world: chunks, terrain generation, persistence and
+/// edits. It has no GPU dependencies, rendering is done by
+/// [`WorldRenderer`](super::renderer::WorldRenderer) which picks up mesh
+/// changes through [`WorldCore::take_dirty_chunks`] and
+/// [`WorldCore::take_unloaded_chunks`].
+pub struct WorldCore {
+    chunks: BTreeMap<Position, Chunk>,
+    pub player: Player,
+
+    render_distance: usize,
+    vertical_render_distance: usize,
+    load_queue: LoadQueue,
+    /// Most chunks queued or being loaded on the loader at once, kept low so
+    /// the load order follows the player.
+    max_pending_chunks: usize,
+    /// Seconds ahead the path of the player is prefetched.
+    prefetch_time: f32,
+    velocity: Vec3<f32>,
+    prev_player_pos: Vec3<f32>,
+    prev_player_chunk: Position,
+    load_stats: LoadStats,
+
+    memory_budget: usize,
+    usage: BTreeMap<Position, ChunkUsage>,
+    /// Totals of [`Chunk::get_voxel_bytes`] and of the mesh bytes in `usage`,
+    /// kept up to date so the budget is checked without walking the chunks.
+    voxel_bytes: usize,
+    mesh_bytes: usize,
+    /// Chunks evicted while in range, not loaded again before they are in
+    /// view, so they are not evicted and loaded in turn.
+    evicted: BTreeSet<Position>,
+    /// Counts updates, chunks in view are marked with it.
+    frame: u64,
+    evicted_chunks: usize,
+    evicted_modified_chunks: usize,
+
+    generation_enabled: bool,
+    generator: Arc<dyn TerrainGenerator>,
+    loader: ChunkLoader,
+    store: Arc<RegionStore>,
+    materials: Arc<MaterialRegistry>,
+
+    dirty_chunks: BTreeSet<Position>,
+    unloaded_chunks: Vec<Position>,
+    /// Chunks meshed while some of their neighbours were missing, with the
+    /// mask of those neighbours (see [`ChunkNeighbourhood::offset_to_index`]).
+    waiting_neighbours: BTreeMap<Position, u32>,
+
+    history: EditHistory,
+    edit_log: Option<EditLog>,
+}
+
+impl WorldCore {
+    pub fn new(generator: Box<dyn TerrainGenerator>, store: RegionStore, player: Player) -> Self {
+        let render_distance = 8;
+        let vertical_render_distance = 4;
+        let generator: Arc<dyn TerrainGenerator> = Arc::from(generator);
+        let store = Arc::new(store);
+        let worker_count = ChunkLoader::get_default_worker_count();
+        let (prev_player_pos, prev_player_chunk) = (player.get_pos(), player.get_chunk_pos());
+        let loader = ChunkLoader::new(generator.clone(), store.clone(), worker_count);
+
+        Self {
+            chunks: BTreeMap::new(),
+            player,
+            render_distance,
+            vertical_render_distance,
+            load_queue: LoadQueue::new(),
+            max_pending_chunks: worker_count * JOBS_PER_WORKER,
+            prefetch_time: 2.,
+            velocity: Vec3::new(0., 0., 0.),
+            prev_player_pos,
+            prev_player_chunk,
+            load_stats: LoadStats::default(),
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            usage: BTreeMap::new(),
+            voxel_bytes: 0,
+            mesh_bytes: 0,
+            evicted: BTreeSet::new(),
+            frame: 0,
+            evicted_chunks: 0,
+            evicted_modified_chunks: 0,
+            generation_enabled: true,
+            generator,
+            loader,
+            store,
+            materials: Arc::new(MaterialRegistry::default()),
+            dirty_chunks: BTreeSet::new(),
+            unloaded_chunks: vec![],
+            waiting_neighbours: BTreeMap::new(),
+            history: EditHistory::default(),
+            edit_log: None,
+        }
+    }
+
+    pub fn get_generator(&self) -> &dyn TerrainGenerator {
+        self.generator.as_ref()
+    }
+
+    pub fn get_materials(&self) -> &MaterialRegistry {
+        &self.materials
+    }
+
+    /// Biome of the terrain at `pos`, see [`TerrainGenerator::get_biome`].
+    pub fn get_biome(&self, pos: Position) -> Option<Biome> {
+        self.generator.get_biome(pos)
+    }
+
+    pub fn get_render_distance(&self) -> usize {
+        self.render_distance
+    }
+
+    pub fn set_render_distance(&mut self, render_distance: usize) {
+        self.render_distance = render_distance;
+    }
+
+    /// Render distance above and below the player, in chunks.
+    pub fn get_vertical_render_distance(&self) -> usize {
+        self.vertical_render_distance
+    }
+
+    pub fn set_vertical_render_distance(&mut self, vertical_render_distance: usize) {
+        self.vertical_render_distance = vertical_render_distance;
+    }
+
+    /// Chunks loaded around the player, an ellipsoid flattened by the
+    /// vertical render distance.
+    pub fn get_load_range(&self) -> LoadRange {
+        LoadRange::new(self.render_distance, self.vertical_render_distance)
+    }
+
+    pub fn is_generation_enabled(&self) -> bool {
+        self.generation_enabled
+    }
+
+    pub fn toggle_generation(&mut self) {
+        self.generation_enabled = !self.generation_enabled;
+    }
+
+    /// Queues the missing chunks around the player on the loader, in the
+    /// order of the [`LoadQueue`], and inserts the chunks it finished without
+    /// waiting for the others. Returns whether chunks are still on their way.
+    pub fn load_chunks(&mut self) -> bool {
+        // the same center as unloading, so loaded chunks are never out of range
+        let player_chunk_pos = self.player.get_chunk_pos();
+
+        let focus = LoadFocus {
+            eye: self.player.get_pos(),
+            look_dir: self.player.get_look_dir(),
+            velocity: self.velocity,
+        };
+        let refilled = self.load_queue.update(
+            player_chunk_pos,
+            self.get_load_range(),
+            focus,
+            self.prefetch_time,
+        );
+        if refilled {
+            let load_queue = &self.load_queue;
+            self.loader.cancel(|pos| load_queue.is_in_range(pos));
+        }
+
+        for chunk in self.loader.collect() {
+            let pos = chunk.get_position();
+            if !self.chunks.contains_key(&pos) && self.load_queue.is_in_range(pos) {
+                self.insert_chunk(chunk);
+            }
+        }
+
+        // chunks in view are never evicted, when they fill the budget
+        // loading waits for them to go out of view
+        while self.loader.get_pending_count() < self.max_pending_chunks && !self.is_over_budget() {
+            let (chunks, loader, evicted) = (&self.chunks, &self.loader, &self.evicted);
+            match self.load_queue.pop(|pos| {
+                !chunks.contains_key(&pos) && !loader.is_pending(pos) && !evicted.contains(&pos)
+            }) {
+                Some(pos) => self.loader.request(pos),
+                None => break,
+            }
+        }
+
+        self.loader.get_pending_count() > 0
+    }
+
+    /// Chunks queued or being loaded on the worker threads.
+    pub fn get_pending_chunk_count(&self) -> usize {
+        self.loader.get_pending_count()
+    }
+
+    fn insert_chunk(&mut self, chunk: Chunk) {
+        let pos = chunk.get_position();
+        self.dirty_chunks.insert(pos);
+        self.voxel_bytes += chunk.get_voxel_bytes();
+        if let Some(replaced) = self.chunks.insert(pos, chunk) {
+            self.voxel_bytes -= replaced.get_voxel_bytes();
+        }
+        // counts as viewed when loaded, so it isn't evicted right away
+        let usage = ChunkUsage {
+            last_viewed: self.frame,
+            mesh_bytes: 0,
+        };
+        if let Some(replaced) = self.usage.insert(pos, usage) {
+            self.mesh_bytes -= replaced.mesh_bytes;
+        }
+        self.evicted.remove(&pos);
+
+        for neighbour_pos in pos.iter_neighbors(false) {
+            let bit = 1 << ChunkNeighbourhood::offset_to_index(pos - neighbour_pos);
+            if let Some(mask) = self.waiting_neighbours.get_mut(&neighbour_pos) {
+                if *mask & bit != 0 {
+                    *mask &= !bit;
+                    self.dirty_chunks.insert(neighbour_pos);
+                }
+            }
+        }
+    }
+
+    /// Returns the chunk at `chunk_pos`, loading it right away if needed.
+    pub fn load_chunk_now(&mut self, chunk_pos: Position) -> &mut Chunk {
+        if !self.chunks.contains_key(&chunk_pos) {
+            let mut chunk = Chunk::new(chunk_pos);
+            chunk.load(&self.store, self.generator.as_ref());
+            self.loader.forget(chunk_pos);
+            self.insert_chunk(chunk);
+        }
+        self.chunks.get_mut(&chunk_pos).unwrap()
+    }
+
+    fn get_chunks_to_unload(&self) -> Vec<Position> {
+        self.chunks
+            .keys()
+            .filter(|pos| !self.load_queue.is_in_range(**pos))
+            .copied()
+            .collect()
+    }
+
+    /// Unloads every chunk out of range at once, so they don't pile up after
+    /// a teleport. Returns the number of unloaded chunks.
+    pub fn unload_chunks(&mut self) -> usize {
+        let positions = self.get_chunks_to_unload();
+        let removed: Vec<Chunk> = positions
+            .iter()
+            .filter_map(|pos| self.remove_chunk(*pos))
+            .collect();
+        self.save_chunks(&removed);
+        positions.len()
+    }
+
+    /// Removes the chunk from the world, saving it first if it was edited.
+    pub fn unload_chunk(&mut self, pos: Position) {
+        if let Some(chunk) = self.remove_chunk(pos) {
+            self.save_chunks(&[chunk]);
+        }
+    }
+
+    /// Removes the chunk from the world without saving it.
+    fn remove_chunk(&mut self, pos: Position) -> Option<Chunk> {
+        let chunk = self.chunks.remove(&pos)?;
+        self.dirty_chunks.remove(&pos);
+        self.waiting_neighbours.remove(&pos);
+        self.voxel_bytes -= chunk.get_voxel_bytes();
+        if let Some(usage) = self.usage.remove(&pos) {
+            self.mesh_bytes -= usage.mesh_bytes;
+        }
+        self.unloaded_chunks.push(pos);
+        Some(chunk)
+    }
+
+    /// Saves the modified chunks among `chunks`, one write per region file so
+    /// unloading many chunks of a region in a frame doesn't rewrite it for
+    /// each of them.
+    fn save_chunks(&self, chunks: &[Chunk]) {
+        let modified: Vec<&Chunk> = chunks.iter().filter(|chunk| chunk.is_modified()).collect();
+        if modified.is_empty() {
+            return;
+        }
+        if let Err(err) = self
+            .store
+            .save_chunks(modified.iter().copied(), self.generator.as_ref())
+        {
+            log::error!("failed to save {} unloaded chunks: {}", modified.len(), err);
+        }
+    }
+
+    /// Writes every modified chunk that is still loaded to the region store,
+    /// along with the inventory of the player. Chunks that fail to save stay
+    /// modified, and the first error is returned once everything has been
+    /// tried.
+    pub fn save(&mut self) -> io::Result<()> {
+        let log_result = match self.edit_log.as_mut() {
+            Some(edit_log) => edit_log.flush(),
+            None => Ok(()),
+        };
+        let mut result = log_result.and(self.store.save_inventory(&self.player.inventory));
+
+        for chunk in self.chunks.values_mut() {
+            if !chunk.is_modified() {
+                continue;
+            }
+            match self.store.save_chunk(chunk, self.generator.as_ref()) {
+                Ok(()) => chunk.set_modified(false),
+                Err(err) => {
+                    log::error!("failed to save chunk {:?}: {}", chunk.get_position(), err);
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.track_player(dt);
+        self.mark_viewed_chunks();
+        if self.generation_enabled {
+            self.load_chunks();
+            self.unload_chunks();
+        }
+        self.evict_chunks();
+    }
+
+    pub fn get_memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// Sets the bytes of voxels and meshes kept loaded. Above it the least
+    /// recently viewed chunks are unloaded, see [`WorldCore::evict_chunks`].
+    pub fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.memory_budget = memory_budget;
+    }
+
+    pub fn get_memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            resident_chunks: self.chunks.len(),
+            voxel_bytes: self.voxel_bytes,
+            mesh_bytes: self.mesh_bytes,
+            evicted_chunks: self.evicted_chunks,
+            evicted_modified_chunks: self.evicted_modified_chunks,
+        }
+    }
+
+    fn get_resident_bytes(&self) -> usize {
+        self.voxel_bytes + self.mesh_bytes
+    }
+
+    fn is_over_budget(&self) -> bool {
+        self.get_resident_bytes() > self.memory_budget
+    }
+
+    /// Records the size of the mesh of the chunk at `pos`, counted in the
+    /// memory budget.
+    pub fn set_mesh_bytes(&mut self, pos: Position, bytes: usize) {
+        if let Some(usage) = self.usage.get_mut(&pos) {
+            self.mesh_bytes = self.mesh_bytes - usage.mesh_bytes + bytes;
+            usage.mesh_bytes = bytes;
+        }
+    }
+
+    /// Whether the chunk at `chunk_pos` is around the player or in front of
+    /// them.
+    fn is_in_view(&self, chunk_pos: Position) -> bool {
+        let size = CHUNK_REAL_SIZE as f32;
+        let center = Vec3::new(
+            (chunk_pos.x as f32 + 0.5) * size,
+            (chunk_pos.y as f32 + 0.5) * size,
+            (chunk_pos.z as f32 + 0.5) * size,
+        );
+        let to_chunk = center - self.player.get_pos();
+        to_chunk.length() <= size || to_chunk.cos(self.player.get_look_dir()) >= VIEW_COS
+    }
+
+    /// Marks the chunks in view with the current frame. Evicted chunks coming
+    /// into view are queued again, the ones out of range are forgotten.
+    fn mark_viewed_chunks(&mut self) {
+        self.frame += 1;
+
+        let viewed: Vec<Position> = self
+            .chunks
+            .keys()
+            .filter(|pos| self.is_in_view(**pos))
+            .copied()
+            .collect();
+        for pos in viewed {
+            if let Some(usage) = self.usage.get_mut(&pos) {
+                usage.last_viewed = self.frame;
+            }
+        }
+
+        let before = self.evicted.len();
+        let evicted = mem::take(&mut self.evicted);
+        self.evicted = evicted
+            .into_iter()
+            .filter(|pos| self.load_queue.is_in_range(*pos) && !self.is_in_view(*pos))
+            .collect();
+        if self.evicted.len() < before {
+            self.load_queue.invalidate();
+        }
+    }
+
+    /// Unloads the least recently viewed chunks until the voxels and meshes
+    /// fit in the memory budget, saving the modified ones first. Chunks in
+    /// view stay. Returns the number of evicted chunks.
+    pub fn evict_chunks(&mut self) -> usize {
+        let resident_bytes = self.get_resident_bytes();
+        if resident_bytes <= self.memory_budget {
+            return 0;
+        }
+        let mut over = resident_bytes - self.memory_budget;
+
+        let mut candidates: Vec<(u64, Position)> = self
+            .usage
+            .iter()
+            .filter(|(_, usage)| usage.last_viewed < self.frame)
+            .map(|(pos, usage)| (usage.last_viewed, *pos))
+            .collect();
+        candidates.sort();
+
+        let mut removed = vec![];
+        for (_, pos) in candidates {
+            if over == 0 {
+                break;
+            }
+            let bytes = self.usage[&pos].mesh_bytes;
+            let chunk = match self.remove_chunk(pos) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            if chunk.is_modified() {
+                self.evicted_modified_chunks += 1;
+            }
+            if self.load_queue.is_in_range(pos) {
+                self.evicted.insert(pos);
+            }
+            over = over.saturating_sub(chunk.get_voxel_bytes() + bytes);
+            removed.push(chunk);
+        }
+        self.save_chunks(&removed);
+        self.evicted_chunks += removed.len();
+        removed.len()
+    }
+
+    /// Updates the velocity of the player from how far they moved in `dt`
+    /// seconds, and counts the chunks they enter.
+    fn track_player(&mut self, dt: f32) {
+        let pos = self.player.get_pos();
+        let moved = pos - self.prev_player_pos;
+        self.prev_player_pos = pos;
+
+        if dt > 0. {
+            let velocity = match moved.length() > TELEPORT_DISTANCE {
+                true => Vec3::new(0., 0., 0.),
+                false => moved / dt,
+            };
+            // smoothed so the prediction doesn't jump with the frame time
+            let blend = dt / (dt + VELOCITY_SMOOTHING);
+            self.velocity = self.velocity + (velocity - self.velocity) * blend;
+        }
+
+        let chunk_pos = self.player.get_chunk_pos();
+        if chunk_pos != self.prev_player_chunk {
+            self.prev_player_chunk = chunk_pos;
+            self.load_stats.chunks_entered += 1;
+            if !self.chunks.contains_key(&chunk_pos) {
+                self.load_stats.chunks_missed += 1;
+            }
+        }
+    }
+
+    /// Velocity of the player in voxels per second, as tracked by
+    /// [`WorldCore::update`].
+    pub fn get_velocity(&self) -> Vec3<f32> {
+        self.velocity
+    }
+
+    pub fn get_prefetch_time(&self) -> f32 {
+        self.prefetch_time
+    }
+
+    /// Sets how many seconds ahead chunks are loaded along the path of the
+    /// player, 0 only loads around them.
+    pub fn set_prefetch_time(&mut self, prefetch_time: f32) {
+        self.prefetch_time = prefetch_time.max(0.);
+    }
+
+    pub fn get_load_stats(&self) -> LoadStats {
+        self.load_stats
+    }
+
+    /// Chunks whose voxels changed since the last call and need a new mesh.
+    pub fn take_dirty_chunks(&mut self) -> Vec<Position> {
+        std::mem::take(&mut self.dirty_chunks).into_iter().collect()
+    }
+
+    /// Records which missing neighbours the last mesh of the chunk at `pos`
+    /// sampled, so it is remeshed once they are loaded.
+    pub fn set_missing_neighbours(&mut self, pos: Position, mask: u32) {
+        if mask == 0 {
+            self.waiting_neighbours.remove(&pos);
+        } else if self.chunks.contains_key(&pos) {
+            self.waiting_neighbours.insert(pos, mask);
+        }
+    }
+
+    /// Chunks removed from the world since the last call.
+    pub fn take_unloaded_chunks(&mut self) -> Vec<Position> {
+        std::mem::take(&mut self.unloaded_chunks)
+    }
+
+    /// Casts a ray through the loaded voxels, see [`raycast::cast_ray`].
+    pub fn cast_ray(&self, from: Vec3<f32>, dir: Vec3<f32>, max_dist: f32) -> RayCast {
+        raycast::cast_ray(from, dir, max_dist, |pos| self.get_voxel(pos))
+    }
+
+    /// Writes `voxel` at the global `pos` into every chunk that stores it,
+    /// loading them if needed. The edit is recorded in the open stroke of the
+    /// history, or as a stroke of its own, and in the edit log.
+    pub fn set_voxel(&mut self, pos: Position, voxel: Voxel) {
+        let own_stroke = !self.history.is_recording();
+        self.history.begin_stroke();
+
+        let before = self.load_voxel(pos);
+        self.history.record(pos, before, voxel);
+        self.write_voxel(pos, voxel);
+
+        if own_stroke {
+            self.history.end_stroke();
+        }
+        self.log_edit(Edit::Voxels(vec![(pos, voxel)]));
+    }
+
+    /// Runs `modify` on every voxel between `min` and `max` (inclusive). It
+    /// returns whether it changed the voxel, changed voxels are written to
+    /// every chunk storing them, so shared border voxels stay the same.
+    ///
+    /// Like [`WorldCore::set_voxel`] the changes are recorded in the history
+    /// and in the edit log. Returns the number of changed voxels.
+    pub fn modify_voxels<F>(&mut self, min: Position, max: Position, modify: F) -> usize
+    where
+        F: FnMut(Position, &mut Voxel) -> bool,
+    {
+        let voxels = self.edit_voxels(min, max, modify);
+        let count = voxels.len();
+        if count > 0 {
+            self.log_edit(Edit::Voxels(voxels));
+        }
+        count
+    }
+
+    /// [`WorldCore::modify_voxels`] without logging, returns the changed
+    /// voxels.
+    fn edit_voxels<F>(
+        &mut self,
+        min: Position,
+        max: Position,
+        mut modify: F,
+    ) -> Vec<(Position, Voxel)>
+    where
+        F: FnMut(Position, &mut Voxel) -> bool,
+    {
+        let own_stroke = !self.history.is_recording();
+        self.history.begin_stroke();
+        let mut voxels = vec![];
+
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let pos = Position::new(x, y, z);
+                    let before = self.load_voxel(pos);
+                    let mut voxel = before;
+
+                    if modify(pos, &mut voxel) {
+                        self.history.record(pos, before, voxel);
+                        self.write_voxel(pos, voxel);
+                        voxels.push((pos, voxel));
+                    }
+                }
+            }
+        }
+
+        if own_stroke {
+            self.history.end_stroke();
+        }
+        voxels
+    }
+
+    fn load_voxel(&mut self, pos: Position) -> Voxel {
+        let chunk = self.load_chunk_now(Chunk::get_chunk_pos(pos));
+        chunk.get_voxel(Chunk::get_in_chunk_pos(pos)).unwrap()
+    }
+
+    fn write_voxel(&mut self, pos: Position, voxel: Voxel) {
+        for chunk_pos in Chunk::get_owning_chunks(pos) {
+            let in_chunk_pos = pos - chunk_pos.mul_scalar(CHUNK_REAL_SIZE as i64);
+            self.load_chunk_now(chunk_pos)
+                .set_voxel(in_chunk_pos, voxel);
+            self.dirty_chunks.insert(chunk_pos);
+        }
+
+        // smooth normals sample the density one voxel away, so the meshes of
+        // the chunks holding the neighbours change too
+        for dir in Direction::iter() {
+            for chunk_pos in Chunk::get_owning_chunks(pos.get_neighbor(dir)) {
+                if self.chunks.contains_key(&chunk_pos) {
+                    self.dirty_chunks.insert(chunk_pos);
+                }
+            }
+        }
+    }
+
+    pub fn get_history(&self) -> &EditHistory {
+        &self.history
+    }
+
+    pub fn get_history_mut(&mut self) -> &mut EditHistory {
+        &mut self.history
+    }
+
+    /// Groups the following edits into one undo step, until
+    /// [`WorldCore::end_stroke`].
+    pub fn begin_stroke(&mut self) {
+        self.history.begin_stroke();
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.history.end_stroke();
+    }
+
+    /// Reverts the last edit stroke, returns `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo() {
+            Some(voxels) => {
+                self.restore_voxels(voxels);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapplies the last undone edit stroke, returns `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo() {
+            Some(voxels) => {
+                self.restore_voxels(voxels);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes voxels taken from the history, the inventory of the player
+    /// pays for the difference. They are logged as they are, so replaying
+    /// doesn't depend on the history.
+    fn restore_voxels(&mut self, voxels: Vec<(Position, Voxel)>) {
+        for (pos, voxel) in voxels.iter() {
+            let before = self.load_voxel(*pos);
+            self.player.inventory.exchange(before, *voxel);
+            self.write_voxel(*pos, *voxel);
+        }
+        self.log_edit(Edit::Voxels(voxels));
+    }
+
+    /// Applies `brush` around `center`, the one path every brush edit goes
+    /// through. Voxels of undiggable materials are left as they are and
+    /// reported.
+    ///
+    /// The inventory of the player pays for added terrain and gets the
+    /// removed terrain.
+    pub fn apply_brush(&mut self, brush: &Brush, center: Position) -> EditReport {
+        if self.edit_log.is_some() {
+            self.log_edit(Edit::Brush {
+                center,
+                brush: *brush,
+                inventory: self.player.inventory.clone(),
+            });
+        }
+        let extent = brush.get_extent();
+        let (min, max) = (center - extent, center + extent);
+
+        let averages = match brush.mode {
+            BrushMode::Smooth => self.get_neighbour_averages(min, max),
+            _ => vec![],
+        };
+        let size = max - min + Position::new(1, 1, 1);
+        let materials = self.materials.clone();
+        let mut inventory = mem::take(&mut self.player.inventory);
+        let mut resisted = vec![];
+
+        let changed = self.edit_voxels(min, max, |pos, voxel| {
+            let offset = pos - min;
+            let index = (offset.x + (offset.y + offset.z * size.y) * size.x) as usize;
+            let average = averages.get(index).copied().unwrap_or(voxel.value);
+            match brush.apply(voxel, pos - center, average, &materials, &mut inventory) {
+                EditOutcome::Changed => true,
+                EditOutcome::Resisted => {
+                    resisted.push(pos);
+                    false
+                }
+                EditOutcome::Unchanged => false,
+            }
+        });
+
+        self.player.inventory = inventory;
+        EditReport {
+            changed: changed.len(),
+            resisted,
+        }
+    }
+
+    /// Mean value of the 26 neighbours of every voxel between `min` and `max`,
+    /// read before any of them is changed. Ordered like the voxels of the box,
+    /// x first then y then z.
+    fn get_neighbour_averages(&mut self, min: Position, max: Position) -> Vec<f32> {
+        // every value is read once, with a margin of a voxel for the neighbours
+        let (low, high) = (min - Position::new(1, 1, 1), max + Position::new(1, 1, 1));
+        let size = high - low + Position::new(1, 1, 1);
+        let mut values = Vec::with_capacity((size.x * size.y * size.z) as usize);
+        for z in low.z..=high.z {
+            for y in low.y..=high.y {
+                for x in low.x..=high.x {
+                    values.push(self.load_voxel(Position::new(x, y, z)).value);
+                }
+            }
+        }
+        let index = |x: i64, y: i64, z: i64| (x + (y + z * size.y) * size.x) as usize;
+
+        let mut averages = Vec::with_capacity(values.len());
+        for z in 1..size.z - 1 {
+            for y in 1..size.y - 1 {
+                for x in 1..size.x - 1 {
+                    let mut sum = 0.;
+                    for offset in Position::new(0, 0, 0).iter_neighbors(false) {
+                        sum += values[index(x + offset.x, y + offset.y, z + offset.z)];
+                    }
+                    averages.push(sum / 26.);
+                }
+            }
+        }
+        averages
+    }
+
+    /// Digs a sphere, see [`WorldCore::apply_brush`].
+    pub fn dig(&mut self, center: Position, radius: f32, value: f32) -> EditReport {
+        self.apply_brush(&Brush::sphere(BrushMode::Subtract, radius, value), center)
+    }
+
+    /// Fills a sphere with the material of `voxel`, see
+    /// [`WorldCore::apply_brush`]. Returns the number of filled voxels.
+    pub fn fill(&mut self, center: Position, radius: f32, voxel: Voxel, value: f32) -> usize {
+        let brush = Brush {
+            material: voxel.material,
+            ..Brush::sphere(BrushMode::Add, radius, value)
+        };
+        self.apply_brush(&brush, center).changed
+    }
+
+    /// Appends every following edit of the world to `edit_log`: brushes,
+    /// undo, redo and voxel writes.
+    pub fn set_edit_log(&mut self, edit_log: EditLog) {
+        self.edit_log = Some(edit_log);
+    }
+
+    fn log_edit(&mut self, edit: Edit) {
+        let edit_log = match self.edit_log.as_mut() {
+            Some(edit_log) => edit_log,
+            None => return,
+        };
+
+        let record = EditRecord {
+            time: Utc::now(),
+            edit,
+        };
+        if let Err(err) = edit_log.append(&record) {
+            log::error!("failed to write edit log: {}", err);
+        }
+    }
+
+    /// Runs the edits of `records` in order. They are not written to the edit
+    /// log again and don't use the inventory of the player, each edit gets
+    /// the inventory it had when recorded.
+    pub fn replay_edits(&mut self, records: &[EditRecord]) {
+        let edit_log = self.edit_log.take();
+        let inventory = mem::take(&mut self.player.inventory);
+
+        for record in records {
+            match &record.edit {
+                Edit::Brush {
+                    center,
+                    brush,
+                    inventory,
+                } => {
+                    self.player.inventory = inventory.clone();
+                    self.apply_brush(brush, *center);
+                }
+                Edit::Voxels(voxels) => {
+                    for (pos, voxel) in voxels.iter() {
+                        self.write_voxel(*pos, *voxel);
+                    }
+                }
+            }
+        }
+
+        self.edit_log = edit_log;
+        self.player.inventory = inventory;
+    }
+
+    /// Applies the brush of the player at the voxel they are looking at, its
+    /// strength is per second. `inverse` swaps adding and subtracting.
+    ///
+    /// Unless a material was picked, terrain is added with the material of
+    /// the targeted voxel.
+    pub fn use_brush(&mut self, dt: f32, inverse: bool) -> Option<EditReport> {
+        let pos = self.player.get_pos();
+        let dir = self.player.get_look_dir();
+
+        let hit = self.cast_ray(pos, dir, 32.).hit?;
+        let mut brush = self.player.brush;
+        brush.strength *= dt;
+        if inverse {
+            brush.mode = brush.mode.get_inverse();
+        }
+        if brush.mode == BrushMode::Add && !self.player.material_picked {
+            brush.material = hit.voxel.material;
+        }
+
+        Some(self.apply_brush(&brush, Self::get_nearest_voxel_pos(hit.position)))
+    }
+
+    /// Makes the material the player is looking at the one of their brush.
+    /// Picking nothing goes back to filling with the targeted material.
+    pub fn pick_material(&mut self) -> Option<VoxelId> {
+        let pos = self.player.get_pos();
+        let dir = self.player.get_look_dir();
+
+        let hit = self.cast_ray(pos, dir, 32.).hit;
+        self.player.material_picked = hit.is_some();
+        let material = hit?.voxel.material;
+        self.player.brush.material = material;
+        Some(material)
+    }
+
+    pub fn get_nearest_voxel_pos(vec: Vec3<f32>) -> Position {
+        Position::new(
+            vec.x.round() as i64,
+            vec.y.round() as i64,
+            vec.z.round() as i64,
+        )
+    }
+
+    pub fn get_chunk_cord_by_vec(vec: Vec3<f32>) -> Position {
+        Position::new(
+            (vec.x / CHUNK_REAL_SIZE as f32).floor() as i64,
+            (vec.y / CHUNK_REAL_SIZE as f32).floor() as i64,
+            (vec.z / CHUNK_REAL_SIZE as f32).floor() as i64,
+        )
+    }
+
+    pub fn get_chunk_by_vec(&self, vec: Vec3<f32>) -> Option<&Chunk> {
+        self.chunks.get(&Self::get_chunk_cord_by_vec(vec))
+    }
+
+    pub fn get_chunk(&self, chunk_pos: Position) -> Option<&Chunk> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    pub fn get_neighbourhood(&self, chunk_pos: Position) -> Option<ChunkNeighbourhood<'_>> {
+        let chunk = self.get_chunk(chunk_pos)?;
+        Some(ChunkNeighbourhood::new(chunk, |pos| self.get_chunk(pos)))
+    }
+
+    pub fn get_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    /// Voxel at the global `pos`, read from any loaded chunk storing it.
+    pub fn get_voxel(&self, pos: Position) -> Option<Voxel> {
+        Chunk::get_owning_chunks(pos)
+            .into_iter()
+            .rev()
+            .find_map(|chunk_pos| {
+                let chunk = self.get_chunk(chunk_pos)?;
+                chunk.get_voxel(pos - chunk_pos.mul_scalar(CHUNK_REAL_SIZE as i64))
+            })
+    }
+}
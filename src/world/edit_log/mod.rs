@@ -0,0 +1,243 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{
+    player::inventory::Inventory,
+    utils::position::Position,
+    world::{
+        brush::{Brush, BrushMode, BrushShape, Falloff},
+        voxel::Voxel,
+    },
+};
+
+/// Name of the edit log inside a world save directory.
+pub const EDIT_LOG_FILE: &str = "edits.log";
+
+/// Second field of the lines of voxels written as they are.
+const VOXELS_FIELD: &str = "voxels";
+
+/// What an [`EditRecord`] changed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// A brush applied around `center`, as passed to
+    /// [`WorldCore::apply_brush`](super::core::WorldCore::apply_brush).
+    Brush {
+        center: Position,
+        brush: Brush,
+        /// Inventory of the player before the edit, it limits what is added.
+        inventory: Inventory,
+    },
+    /// Voxels written as they are, by undo, redo and direct voxel writes.
+    Voxels(Vec<(Position, Voxel)>),
+}
+
+/// One edit of the world.
+///
+/// A line holds the time then either the brush mode, center, radius,
+/// strength, material, inventory, shape, falloff and height, or `voxels`
+/// followed by `x,y,z,value,material` for every voxel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditRecord {
+    pub time: DateTime<Utc>,
+    pub edit: Edit,
+}
+
+impl EditRecord {
+    fn encode(&self) -> String {
+        let time = self.time.to_rfc3339_opts(SecondsFormat::Micros, true);
+        match &self.edit {
+            Edit::Brush {
+                center,
+                brush,
+                inventory,
+            } => format!(
+                "{} {} {} {} {} {} {} {} {} {} {} {}",
+                time,
+                brush.mode.get_name(),
+                center.x,
+                center.y,
+                center.z,
+                brush.radius,
+                brush.strength,
+                brush.material,
+                encode_inventory(inventory),
+                brush.shape.get_name(),
+                brush.falloff.get_name(),
+                brush.height,
+            ),
+            Edit::Voxels(voxels) => {
+                let voxels: Vec<String> = voxels
+                    .iter()
+                    .map(|(pos, voxel)| {
+                        format!(
+                            "{},{},{},{},{}",
+                            pos.x, pos.y, pos.z, voxel.value, voxel.material
+                        )
+                    })
+                    .collect();
+                format!("{} {} {}", time, VOXELS_FIELD, voxels.join(" "))
+            }
+        }
+    }
+
+    fn decode(line: &str) -> io::Result<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let time = fields
+            .first()
+            .ok_or_else(|| invalid_data("malformed edit log line"))?;
+        let time = DateTime::parse_from_rfc3339(time)
+            .map_err(|_| invalid_data("malformed edit log time"))?
+            .with_timezone(&Utc);
+
+        let edit = match fields.get(1) {
+            Some(&VOXELS_FIELD) => Edit::Voxels(
+                fields[2..]
+                    .iter()
+                    .map(|field| decode_voxel(field))
+                    .collect::<io::Result<_>>()?,
+            ),
+            _ => decode_brush(&fields[1..])?,
+        };
+        Ok(Self { time, edit })
+    }
+}
+
+fn decode_brush(fields: &[&str]) -> io::Result<Edit> {
+    let [mode, x, y, z, radius, strength, material, inventory, shape, falloff, height]: [&str; 11] =
+        fields
+            .try_into()
+            .map_err(|_| invalid_data("malformed edit log line"))?;
+
+    let brush = Brush {
+        shape: BrushShape::from_name(shape).ok_or_else(|| invalid_data("unknown brush shape"))?,
+        falloff: Falloff::from_name(falloff)
+            .ok_or_else(|| invalid_data("unknown brush falloff"))?,
+        mode: BrushMode::from_name(mode).ok_or_else(|| invalid_data("unknown brush mode"))?,
+        radius: parse(radius)?,
+        height: parse(height)?,
+        strength: parse(strength)?,
+        material: parse(material)?,
+    };
+
+    Ok(Edit::Brush {
+        center: Position::new(parse(x)?, parse(y)?, parse(z)?),
+        brush,
+        inventory: decode_inventory(inventory)?,
+    })
+}
+
+fn decode_voxel(field: &str) -> io::Result<(Position, Voxel)> {
+    let values: Vec<&str> = field.split(',').collect();
+    let [x, y, z, value, material]: [&str; 5] = values
+        .try_into()
+        .map_err(|_| invalid_data("malformed edit log voxel"))?;
+
+    let pos = Position::new(parse(x)?, parse(y)?, parse(z)?);
+    let voxel = Voxel {
+        value: parse(value)?,
+        material: parse(material)?,
+    };
+    Ok((pos, voxel))
+}
+
+/// `-` when unlimited, `empty`, or `material=amount` pairs separated by commas.
+fn encode_inventory(inventory: &Inventory) -> String {
+    if inventory.is_unlimited() {
+        return "-".to_string();
+    }
+    let stock: Vec<String> = inventory
+        .iter()
+        .filter(|(_, amount)| *amount != 0.)
+        .map(|(material, amount)| format!("{}={}", material, amount))
+        .collect();
+    match stock.is_empty() {
+        true => "empty".to_string(),
+        false => stock.join(","),
+    }
+}
+
+fn decode_inventory(value: &str) -> io::Result<Inventory> {
+    let mut inventory = Inventory::new();
+    match value {
+        "-" => return Ok(Inventory::unlimited()),
+        "empty" => {}
+        stock => {
+            for entry in stock.split(',') {
+                let (material, amount) = entry
+                    .split_once('=')
+                    .ok_or_else(|| invalid_data("malformed edit log stock"))?;
+                inventory.add(parse(material)?, parse(amount)?);
+            }
+        }
+    }
+    Ok(inventory)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> io::Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid_data("malformed edit log value"))
+}
+
+/// Append-only text log of the edits of a world, one [`EditRecord`] per line.
+///
+/// Replaying it with [`WorldCore::replay_edits`](super::core::WorldCore::replay_edits)
+/// on top of the terrain of the same generator rebuilds the edited world.
+pub struct EditLog {
+    path: PathBuf,
+    writer: BufWriter<fs::File>,
+}
+
+impl EditLog {
+    /// Opens the log at `path`, new records are appended after existing ones.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `record` through to the file right away, so a crash loses at
+    /// most the edit being written.
+    pub fn append(&mut self, record: &EditRecord) -> io::Result<()> {
+        writeln!(self.writer, "{}", record.encode())?;
+        self.writer.flush()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Reads every record of the log at `path`, oldest first.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<EditRecord>> {
+        fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(EditRecord::decode)
+            .collect()
+    }
+}
@@ -0,0 +1,89 @@
+use strum_macros::{EnumCount as EnumCountMacro, EnumIter};
+
+use crate::world::voxel::{material, VoxelId};
+
+/// Climate zone of the terrain, chosen from temperature and humidity noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumCountMacro, EnumIter)]
+pub enum Biome {
+    Desert,
+    Tundra,
+    Forest,
+    Mountains,
+}
+
+/// Shape and materials of a biome.
+pub struct BiomeParams {
+    /// Temperature and humidity, in `-1..=1`, where the biome is the purest.
+    pub climate: (f64, f64),
+    /// Surface height around which the terrain varies.
+    pub base_height: f64,
+    pub amplitude: f64,
+    pub frequency: f64,
+    /// Sharp ridges instead of rolling hills.
+    pub ridged: bool,
+    /// How much of the 3d cliff noise carves the terrain, in `0..=1`.
+    pub cliffs: f64,
+    pub surface_material: VoxelId,
+    /// Depth of the `surface_material` layer.
+    pub surface_depth: f64,
+    pub ground_material: VoxelId,
+}
+
+const DESERT: BiomeParams = BiomeParams {
+    climate: (0.7, -0.6),
+    base_height: -8.,
+    amplitude: 4.,
+    frequency: 0.02,
+    ridged: false,
+    cliffs: 0.,
+    surface_material: material::SAND,
+    surface_depth: 4.,
+    ground_material: material::SANDSTONE,
+};
+
+const TUNDRA: BiomeParams = BiomeParams {
+    climate: (-0.7, 0.),
+    base_height: -10.,
+    amplitude: 6.,
+    frequency: 0.03,
+    ridged: false,
+    cliffs: 0.3,
+    surface_material: material::SNOW,
+    surface_depth: 2.,
+    ground_material: material::SLATE,
+};
+
+const FOREST: BiomeParams = BiomeParams {
+    climate: (0.3, 0.6),
+    base_height: -10.,
+    amplitude: 10.,
+    frequency: 0.0456,
+    ridged: false,
+    cliffs: 1.,
+    surface_material: material::GRASS,
+    surface_depth: 3.,
+    ground_material: material::ROCK,
+};
+
+const MOUNTAINS: BiomeParams = BiomeParams {
+    climate: (-0.2, -0.6),
+    base_height: 0.,
+    amplitude: 40.,
+    frequency: 0.015,
+    ridged: true,
+    cliffs: 1.,
+    surface_material: material::GRAVEL,
+    surface_depth: 1.,
+    ground_material: material::GRANITE,
+};
+
+impl Biome {
+    pub fn get_params(&self) -> &'static BiomeParams {
+        match self {
+            Biome::Desert => &DESERT,
+            Biome::Tundra => &TUNDRA,
+            Biome::Forest => &FOREST,
+            Biome::Mountains => &MOUNTAINS,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{utils::position::Position, vec::Vec3};
+
+use super::super::voxel::Voxel;
+
+/// Size of the cubes of voxels worms start in.
+const CAVE_REGION_SIZE: i64 = 64;
+const MAX_WORMS_PER_REGION: u64 = 2;
+const MIN_WORM_STEPS: u64 = 40;
+const MAX_WORM_STEPS: u64 = 100;
+/// Distance between two spheres of a worm.
+const WORM_STEP: f64 = 1.;
+const MIN_TUNNEL_RADIUS: f64 = 1.5;
+const MAX_TUNNEL_RADIUS: f64 = 3.5;
+/// Chance for a step of a worm to open into a cavern.
+const CAVERN_CHANCE: f64 = 0.02;
+const MIN_CAVERN_RADIUS: f64 = 5.;
+const MAX_CAVERN_RADIUS: f64 = 9.;
+/// Farthest a worm can carve from its region.
+const MAX_WORM_REACH: f64 = MAX_WORM_STEPS as f64 * WORM_STEP + MAX_CAVERN_RADIUS;
+/// Voxel value change per voxel of distance to a tunnel wall.
+const CAVE_DENSITY_SCALE: f32 = 0.05;
+
+/// Small deterministic random generator (SplitMix64), so worms only depend on
+/// the seed and their region.
+pub(super) struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub(super) fn new(seed: u32, region: Position) -> Self {
+        let mut random = Self { state: seed as u64 };
+        for v in [region.x, region.y, region.z] {
+            random.state ^= v as u64;
+            random.next_u64();
+        }
+        random
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..1`.
+    pub(super) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+/// Sphere carved out of the terrain.
+struct CaveSphere {
+    center: Vec3<f64>,
+    radius: f64,
+}
+
+/// Carves tunnels and caverns into generated voxels.
+///
+/// Every `CAVE_REGION_SIZE`³ region of the world starts a few worms at
+/// positions picked from the seed and the region alone. A chunk is carved by
+/// every worm that can reach it, so the result does not depend on the order
+/// chunks are generated in.
+pub struct CaveCarver {
+    seed: u32,
+}
+
+impl CaveCarver {
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    fn get_region_worms(&self, region: Position) -> Vec<CaveSphere> {
+        let mut random = Random::new(self.seed, region);
+        let mut spheres = vec![];
+
+        let worms = random.next_u64() % (MAX_WORMS_PER_REGION + 1);
+        for _ in 0..worms {
+            let origin = region.mul_scalar(CAVE_REGION_SIZE);
+            let mut pos = Vec3::new(
+                origin.x as f64 + random.range(0., CAVE_REGION_SIZE as f64),
+                origin.y as f64 + random.range(0., CAVE_REGION_SIZE as f64),
+                origin.z as f64 + random.range(0., CAVE_REGION_SIZE as f64),
+            );
+            let mut yaw = random.range(0., std::f64::consts::TAU);
+            let mut pitch = random.range(-0.5, 0.5);
+            let mut radius = random.range(MIN_TUNNEL_RADIUS, MAX_TUNNEL_RADIUS);
+
+            let steps = MIN_WORM_STEPS + random.next_u64() % (MAX_WORM_STEPS - MIN_WORM_STEPS + 1);
+            for _ in 0..steps {
+                if random.next_f64() < CAVERN_CHANCE {
+                    spheres.push(CaveSphere {
+                        center: pos,
+                        radius: random.range(MIN_CAVERN_RADIUS, MAX_CAVERN_RADIUS),
+                    });
+                }
+                spheres.push(CaveSphere {
+                    center: pos,
+                    radius,
+                });
+
+                yaw += random.range(-0.2, 0.2);
+                // tunnels drift back towards horizontal
+                pitch = (pitch + random.range(-0.1, 0.1)) * 0.9;
+                radius =
+                    (radius + random.range(-0.2, 0.2)).clamp(MIN_TUNNEL_RADIUS, MAX_TUNNEL_RADIUS);
+
+                let dir = Vec3::new(
+                    yaw.cos() * pitch.cos(),
+                    pitch.sin(),
+                    yaw.sin() * pitch.cos(),
+                );
+                pos += dir * WORM_STEP;
+            }
+        }
+
+        spheres
+    }
+
+    /// Spheres of every worm touching the box between `min` and `max`.
+    fn get_spheres(&self, min: Vec3<f64>, max: Vec3<f64>) -> Vec<CaveSphere> {
+        let region_range = |min: f64, max: f64| {
+            let from = ((min - MAX_WORM_REACH) / CAVE_REGION_SIZE as f64).floor() as i64;
+            let to = ((max + MAX_WORM_REACH) / CAVE_REGION_SIZE as f64).floor() as i64;
+            from..=to
+        };
+
+        let mut spheres = vec![];
+        for x in region_range(min.x, max.x) {
+            for y in region_range(min.y, max.y) {
+                for z in region_range(min.z, max.z) {
+                    spheres.extend(
+                        self.get_region_worms(Position::new(x, y, z))
+                            .into_iter()
+                            .filter(|sphere| {
+                                let r = sphere.radius;
+                                let c = sphere.center;
+                                c.x + r >= min.x
+                                    && c.x - r <= max.x
+                                    && c.y + r >= min.y
+                                    && c.y - r <= max.y
+                                    && c.z + r >= min.z
+                                    && c.z - r <= max.z
+                            }),
+                    );
+                }
+            }
+        }
+        spheres
+    }
+
+    /// Carves the caves into `voxels`, laid out like in
+    /// [`TerrainGenerator::generate_voxels`](super::TerrainGenerator::generate_voxels).
+    pub fn carve(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
+        let max = offset + Vec3::new(1., 1., 1.) * (size as f64 - 1.);
+        let spheres = self.get_spheres(offset, max);
+        if spheres.is_empty() {
+            return;
+        }
+
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let pos = offset + Vec3::new(x as f64, y as f64, z as f64);
+                    let wall_distance = spheres
+                        .iter()
+                        .map(|sphere| (pos - sphere.center).length() - sphere.radius)
+                        .fold(f64::INFINITY, f64::min);
+
+                    let voxel = &mut voxels[x + y * size + z * size * size];
+                    let carved = wall_distance as f32 * CAVE_DENSITY_SCALE;
+                    voxel.value = voxel.value.min(carved.max(-0.1));
+                }
+            }
+        }
+    }
+}
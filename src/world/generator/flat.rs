@@ -0,0 +1,35 @@
+use crate::vec::Vec3;
+
+use super::{
+    super::voxel::{Voxel, VoxelId},
+    TerrainGenerator,
+};
+
+/// Flat ground of a single material, solid below `height`.
+pub struct FlatGenerator {
+    pub height: f64,
+    pub material: VoxelId,
+}
+
+impl FlatGenerator {
+    pub fn new(height: f64, material: VoxelId) -> Self {
+        Self { height, material }
+    }
+}
+
+impl TerrainGenerator for FlatGenerator {
+    fn generate_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let value = self.height - (offset.y + y as f64);
+
+                    voxels[x + y * size + z * size * size] = Voxel {
+                        value: value.clamp(-0.1, 1.) as f32,
+                        material: self.material,
+                    };
+                }
+            }
+        }
+    }
+}
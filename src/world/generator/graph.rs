@@ -0,0 +1,386 @@
+use std::{cmp::Ordering, collections::BTreeMap, fs, io, path::Path};
+
+use noise::{NoiseFn, Seedable};
+use serde::Deserialize;
+
+use crate::vec::Vec3;
+
+use super::{
+    super::voxel::{material::MaterialRegistry, Voxel, VoxelId},
+    TerrainGenerator,
+};
+
+/// Graph of the default terrain, gives the same voxels as [`super::Generator::new`].
+pub const DEFAULT_TERRAIN_GRAPH: &str = include_str!("default_terrain.ron");
+
+/// Node of a terrain graph, evaluated at the world position of a voxel.
+///
+/// `Add` and `Mul` fold their inputs left to right.
+#[derive(Clone, Debug, Deserialize)]
+pub enum NoiseNode {
+    Constant(f64),
+    X,
+    Y,
+    Z,
+    /// Named node of [`TerrainGraph::nodes`].
+    Ref(String),
+    Add(Vec<NoiseNode>),
+    Mul(Vec<NoiseNode>),
+    Sub(Box<NoiseNode>, Box<NoiseNode>),
+    Div(Box<NoiseNode>, Box<NoiseNode>),
+    Neg(Box<NoiseNode>),
+    Min(Box<NoiseNode>, Box<NoiseNode>),
+    Max(Box<NoiseNode>, Box<NoiseNode>),
+    Clamp {
+        input: Box<NoiseNode>,
+        min: f64,
+        max: f64,
+    },
+    /// `a + (b - a) * t`
+    Blend {
+        a: Box<NoiseNode>,
+        b: Box<NoiseNode>,
+        t: Box<NoiseNode>,
+    },
+    /// Simplex noise sampled at 2 to 4 coordinates.
+    Simplex(Vec<NoiseNode>),
+    /// Sum of `octaves` simplex samples, each one at `lacunarity` times the
+    /// frequency and `persistence` times the amplitude of the previous one.
+    Fbm {
+        input: Vec<NoiseNode>,
+        octaves: u32,
+        lacunarity: f64,
+        persistence: f64,
+    },
+    /// Evaluates `input` at the position moved by `offset`.
+    Warp {
+        input: Box<NoiseNode>,
+        offset: Box<(NoiseNode, NoiseNode, NoiseNode)>,
+    },
+}
+
+/// Voxels get the material of the first rule whose `input` is below `below`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MaterialRule {
+    pub input: NoiseNode,
+    pub below: NoiseNode,
+    pub material: String,
+}
+
+/// Materials are named as in the default [`MaterialRegistry`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct MaterialGraph {
+    pub default: String,
+    #[serde(default)]
+    pub rules: Vec<MaterialRule>,
+}
+
+/// Data description of a terrain, see `default_terrain.ron` for an example.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TerrainGraph {
+    pub seed: u32,
+    #[serde(default)]
+    pub nodes: BTreeMap<String, NoiseNode>,
+    /// Voxel value, clamped to `-0.1..=1` by convention.
+    pub value: NoiseNode,
+    pub material: MaterialGraph,
+}
+
+impl TerrainGraph {
+    pub fn from_ron(data: &str) -> io::Result<Self> {
+        ron::from_str(data).map_err(|err| invalid_data(&err.to_string()))
+    }
+}
+
+/// Hash of the source text of a terrain graph, so a world can tell when its
+/// graph was edited. FNV-1a, which unlike the std hashers is the same on
+/// every build.
+pub fn hash_source(source: &str) -> u64 {
+    source.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// [`NoiseNode`] with the named references resolved.
+enum Node {
+    Constant(f64),
+    Axis(usize),
+    Ref(usize),
+    Add(Vec<Node>),
+    Mul(Vec<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    Neg(Box<Node>),
+    Min(Box<Node>, Box<Node>),
+    Max(Box<Node>, Box<Node>),
+    Clamp(Box<Node>, f64, f64),
+    Blend(Box<Node>, Box<Node>, Box<Node>),
+    Simplex(Vec<Node>),
+    Fbm(Vec<Node>, u32, f64, f64),
+    Warp(Box<Node>, Box<[Node; 3]>),
+}
+
+struct Compiler<'a> {
+    graph: &'a TerrainGraph,
+    names: Vec<&'a String>,
+    compiled: Vec<Option<Node>>,
+    /// Named nodes being compiled, to detect cycles.
+    visiting: Vec<usize>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(graph: &'a TerrainGraph) -> Self {
+        Self {
+            graph,
+            names: graph.nodes.keys().collect(),
+            compiled: graph.nodes.keys().map(|_| None).collect(),
+            visiting: vec![],
+        }
+    }
+
+    fn compile_ref(&mut self, name: &str) -> io::Result<usize> {
+        let index = self
+            .names
+            .iter()
+            .position(|n| n.as_str() == name)
+            .ok_or_else(|| invalid_data(&format!("unknown terrain node '{}'", name)))?;
+
+        if self.compiled[index].is_none() {
+            if self.visiting.contains(&index) {
+                return Err(invalid_data(&format!(
+                    "terrain node '{}' uses itself",
+                    name
+                )));
+            }
+            self.visiting.push(index);
+            let graph = self.graph;
+            let node = self.compile(&graph.nodes[name])?;
+            self.visiting.pop();
+            self.compiled[index] = Some(node);
+        }
+        Ok(index)
+    }
+
+    fn compile_all(&mut self, nodes: &[NoiseNode]) -> io::Result<Vec<Node>> {
+        nodes.iter().map(|node| self.compile(node)).collect()
+    }
+
+    fn compile_box(&mut self, node: &NoiseNode) -> io::Result<Box<Node>> {
+        self.compile(node).map(Box::new)
+    }
+
+    fn compile_coords(&mut self, nodes: &[NoiseNode]) -> io::Result<Vec<Node>> {
+        if !(2..=4).contains(&nodes.len()) {
+            return Err(invalid_data("noise needs 2 to 4 coordinates"));
+        }
+        self.compile_all(nodes)
+    }
+
+    fn compile(&mut self, node: &NoiseNode) -> io::Result<Node> {
+        Ok(match node {
+            NoiseNode::Constant(value) => Node::Constant(*value),
+            NoiseNode::X => Node::Axis(0),
+            NoiseNode::Y => Node::Axis(1),
+            NoiseNode::Z => Node::Axis(2),
+            NoiseNode::Ref(name) => Node::Ref(self.compile_ref(name)?),
+            NoiseNode::Add(nodes) | NoiseNode::Mul(nodes) if nodes.is_empty() => {
+                return Err(invalid_data("Add and Mul need at least one input"))
+            }
+            NoiseNode::Add(nodes) => Node::Add(self.compile_all(nodes)?),
+            NoiseNode::Mul(nodes) => Node::Mul(self.compile_all(nodes)?),
+            NoiseNode::Sub(a, b) => Node::Sub(self.compile_box(a)?, self.compile_box(b)?),
+            NoiseNode::Div(a, b) => Node::Div(self.compile_box(a)?, self.compile_box(b)?),
+            NoiseNode::Neg(a) => Node::Neg(self.compile_box(a)?),
+            NoiseNode::Min(a, b) => Node::Min(self.compile_box(a)?, self.compile_box(b)?),
+            NoiseNode::Max(a, b) => Node::Max(self.compile_box(a)?, self.compile_box(b)?),
+            NoiseNode::Clamp { input, min, max } => {
+                if !matches!(min.partial_cmp(max), Some(Ordering::Less | Ordering::Equal)) {
+                    return Err(invalid_data("Clamp min must not be above max"));
+                }
+                Node::Clamp(self.compile_box(input)?, *min, *max)
+            }
+            NoiseNode::Blend { a, b, t } => Node::Blend(
+                self.compile_box(a)?,
+                self.compile_box(b)?,
+                self.compile_box(t)?,
+            ),
+            NoiseNode::Simplex(coords) => Node::Simplex(self.compile_coords(coords)?),
+            NoiseNode::Fbm {
+                input,
+                octaves,
+                lacunarity,
+                persistence,
+            } => Node::Fbm(
+                self.compile_coords(input)?,
+                *octaves,
+                *lacunarity,
+                *persistence,
+            ),
+            NoiseNode::Warp { input, offset } => Node::Warp(
+                self.compile_box(input)?,
+                Box::new([
+                    self.compile(&offset.0)?,
+                    self.compile(&offset.1)?,
+                    self.compile(&offset.2)?,
+                ]),
+            ),
+        })
+    }
+}
+
+struct CompiledMaterialRule {
+    input: Node,
+    below: Node,
+    material: VoxelId,
+}
+
+/// Terrain generator compiled from a [`TerrainGraph`].
+pub struct GraphGenerator {
+    simplex: noise::OpenSimplex,
+    nodes: Vec<Node>,
+    value: Node,
+    default_material: VoxelId,
+    material_rules: Vec<CompiledMaterialRule>,
+}
+
+impl GraphGenerator {
+    pub fn from_graph(graph: &TerrainGraph) -> io::Result<Self> {
+        let mut compiler = Compiler::new(graph);
+
+        let value = compiler.compile(&graph.value)?;
+        let materials = MaterialRegistry::default();
+        let get_material = |name: &str| {
+            materials
+                .get_id(name)
+                .ok_or_else(|| invalid_data(&format!("unknown material {}", name)))
+        };
+
+        let material_rules = graph
+            .material
+            .rules
+            .iter()
+            .map(|rule| {
+                Ok(CompiledMaterialRule {
+                    input: compiler.compile(&rule.input)?,
+                    below: compiler.compile(&rule.below)?,
+                    material: get_material(&rule.material)?,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // unused named nodes are still checked
+        for name in graph.nodes.keys() {
+            compiler.compile_ref(name)?;
+        }
+
+        Ok(Self {
+            simplex: noise::OpenSimplex::new().set_seed(graph.seed),
+            nodes: compiler.compiled.into_iter().map(Option::unwrap).collect(),
+            value,
+            default_material: get_material(&graph.material.default)?,
+            material_rules,
+        })
+    }
+
+    pub fn from_ron(data: &str) -> io::Result<Self> {
+        Self::from_graph(&TerrainGraph::from_ron(data)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+
+    fn sample_simplex(&self, coords: &[f64]) -> f64 {
+        match *coords {
+            [x, y] => self.simplex.get([x, y]),
+            [x, y, z] => self.simplex.get([x, y, z]),
+            [x, y, z, w] => self.simplex.get([x, y, z, w]),
+            _ => unreachable!("checked when compiling"),
+        }
+    }
+
+    fn eval(&self, node: &Node, pos: [f64; 3]) -> f64 {
+        match node {
+            Node::Constant(value) => *value,
+            Node::Axis(axis) => pos[*axis],
+            Node::Ref(index) => self.eval(&self.nodes[*index], pos),
+            Node::Add(nodes) => self.fold(nodes, pos, |a, b| a + b),
+            Node::Mul(nodes) => self.fold(nodes, pos, |a, b| a * b),
+            Node::Sub(a, b) => self.eval(a, pos) - self.eval(b, pos),
+            Node::Div(a, b) => self.eval(a, pos) / self.eval(b, pos),
+            Node::Neg(a) => -self.eval(a, pos),
+            Node::Min(a, b) => self.eval(a, pos).min(self.eval(b, pos)),
+            Node::Max(a, b) => self.eval(a, pos).max(self.eval(b, pos)),
+            Node::Clamp(input, min, max) => self.eval(input, pos).clamp(*min, *max),
+            Node::Blend(a, b, t) => {
+                let a = self.eval(a, pos);
+                a + (self.eval(b, pos) - a) * self.eval(t, pos)
+            }
+            Node::Simplex(coords) => {
+                let coords: Vec<f64> = coords.iter().map(|c| self.eval(c, pos)).collect();
+                self.sample_simplex(&coords)
+            }
+            Node::Fbm(coords, octaves, lacunarity, persistence) => {
+                let coords: Vec<f64> = coords.iter().map(|c| self.eval(c, pos)).collect();
+                let mut frequency = 1.;
+                let mut amplitude = 1.;
+                let mut sum = 0.;
+                for _ in 0..*octaves {
+                    let scaled: Vec<f64> = coords.iter().map(|c| c * frequency).collect();
+                    sum += self.sample_simplex(&scaled) * amplitude;
+                    frequency *= lacunarity;
+                    amplitude *= persistence;
+                }
+                sum
+            }
+            Node::Warp(input, offset) => {
+                let warped = [
+                    pos[0] + self.eval(&offset[0], pos),
+                    pos[1] + self.eval(&offset[1], pos),
+                    pos[2] + self.eval(&offset[2], pos),
+                ];
+                self.eval(input, warped)
+            }
+        }
+    }
+
+    fn fold<F: Fn(f64, f64) -> f64>(&self, nodes: &[Node], pos: [f64; 3], op: F) -> f64 {
+        nodes[1..]
+            .iter()
+            .fold(self.eval(&nodes[0], pos), |acc, node| {
+                op(acc, self.eval(node, pos))
+            })
+    }
+
+    fn get_material(&self, pos: [f64; 3]) -> VoxelId {
+        self.material_rules
+            .iter()
+            .find(|rule| self.eval(&rule.input, pos) < self.eval(&rule.below, pos))
+            .map_or(self.default_material, |rule| rule.material)
+    }
+}
+
+impl TerrainGenerator for GraphGenerator {
+    fn generate_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let pos = [
+                        offset.x + x as f64,
+                        offset.y + y as f64,
+                        offset.z + z as f64,
+                    ];
+
+                    voxels[x + y * size + z * size * size] = Voxel {
+                        value: self.eval(&self.value, pos) as f32,
+                        material: self.get_material(pos),
+                    };
+                }
+            }
+        }
+    }
+}
@@ -1,70 +1,260 @@
-use noise::NoiseFn;
+use noise::{NoiseFn, Seedable};
+use strum::{EnumCount, IntoEnumIterator};
 
-use crate::vec::Vec3;
+use crate::{utils::position::Position, vec::Vec3};
 
-use super::voxel::Voxel;
+use self::{
+    biome::Biome,
+    caves::{CaveCarver, Random},
+};
 
+use super::voxel::{
+    material::{GRASS, ROCK},
+    Voxel, VoxelId,
+};
+
+pub mod biome;
+pub mod caves;
+pub mod flat;
+pub mod graph;
+
+/// Source of the terrain of a world. It has to give the same voxels for the
+/// same offset every time, stored chunks are saved as a diff against it.
+pub trait TerrainGenerator: Send + Sync {
+    /// Fills `voxels`, a cube of `size`³ voxels indexed by
+    /// `x + y * size + z * size * size`, with the terrain starting at `offset`.
+    fn generate_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize);
+
+    /// Biome of the terrain at `pos`, `None` for terrains without biomes.
+    fn get_biome(&self, _pos: Position) -> Option<Biome> {
+        None
+    }
+
+    /// Amplitude of the color noise the terrain is meshed with, `None` to
+    /// keep the mesher default.
+    fn get_color_variation(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// Frequency of the temperature and humidity noise, biomes are a few hundred
+/// voxels wide.
+const CLIMATE_FREQUENCY: f64 = 0.002;
+/// Distance in climate space over which neighbouring biomes blend.
+const BIOME_BLEND_WIDTH: f64 = 0.3;
+
+type BiomeWeights = [f64; Biome::COUNT];
+
+/// Biome blend of a column of voxels.
+struct Column {
+    surface: f64,
+    cliffs: f64,
+    /// Biome the materials of the column are taken from.
+    material_biome: Biome,
+}
+
+/// Everything needed to regenerate exactly the same terrain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeneratorParams {
+    pub seed: u32,
+    pub scale: f64,
+    pub noise_threshold: f64,
+    /// Surface level (relative to `scale`) below which voxels are made of grass.
+    pub grass_level: f64,
+    pub grass_material: VoxelId,
+    pub rock_material: VoxelId,
+    /// Amplitude of the per-voxel color noise added to the materials.
+    pub color_variation: f64,
+    /// Shape the terrain and pick its materials by biomes, instead of grass hills
+    /// everywhere. The grass params are not used then.
+    pub biomes: bool,
+    /// Carve tunnels and caverns, see [`CaveCarver`].
+    pub caves: bool,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            scale: 0.1,
+            noise_threshold: 0.6,
+            grass_level: 0.03,
+            grass_material: GRASS,
+            rock_material: ROCK,
+            color_variation: 0.05,
+            biomes: false,
+            caves: false,
+        }
+    }
+}
+
+impl GeneratorParams {
+    pub fn with_seed(seed: u32) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+}
+
+/// Default terrain: simplex noise hills with cliffs, optionally split into
+/// biomes.
 pub struct Generator {
-    scale: f64,
-    noise_threshold: f64,
+    params: GeneratorParams,
     simplex: noise::OpenSimplex,
+    temperature: noise::OpenSimplex,
+    humidity: noise::OpenSimplex,
+    caves: Option<CaveCarver>,
 }
 
 impl Generator {
     pub fn new() -> Self {
+        Self::from_params(GeneratorParams::default())
+    }
+
+    pub fn with_seed(seed: u32) -> Self {
+        Self::from_params(GeneratorParams::with_seed(seed))
+    }
+
+    pub fn from_params(params: GeneratorParams) -> Self {
         Self {
-            scale: 0.1,
-            noise_threshold: 0.6,
-            simplex: noise::OpenSimplex::new(),
+            params,
+            simplex: noise::OpenSimplex::new().set_seed(params.seed),
+            temperature: noise::OpenSimplex::new().set_seed(params.seed.wrapping_add(1)),
+            humidity: noise::OpenSimplex::new().set_seed(params.seed.wrapping_add(2)),
+            caves: params.caves.then(|| CaveCarver::new(params.seed)),
         }
     }
 
+    pub fn get_params(&self) -> GeneratorParams {
+        self.params
+    }
+
     fn get_level_val(&self, pos: Vec3<f64>) -> f64 {
         let mut noise_v = pos.y
-            + (self
-                .simplex
-                .get([pos.x * 0.456 * self.scale, pos.z * 0.456 * self.scale])
-                + 1.)
+            + (self.simplex.get([
+                pos.x * 0.456 * self.params.scale,
+                pos.z * 0.456 * self.params.scale,
+            ]) + 1.)
                 * 10.
             + (self.simplex.get([pos.x, pos.z]) + 1.) * 0.001;
 
-        noise_v -= self.noise_threshold;
-        noise_v /= 100. / self.scale;
+        noise_v -= self.params.noise_threshold;
+        noise_v /= 100. / self.params.scale;
 
         return -noise_v;
     }
 
     fn get_cliffs_val(&self, pos: Vec3<f64>) -> f64 {
-        let mut noise_v =
-            self.simplex
-                .get([pos.x * self.scale, pos.y * self.scale, pos.z * self.scale]);
+        let mut noise_v = self.simplex.get([
+            pos.x * self.params.scale,
+            pos.y * self.params.scale,
+            pos.z * self.params.scale,
+        ]);
         noise_v += 1.0;
         noise_v /= 2.0;
 
         noise_v -= (self.simplex.get([
-            pos.x * 0.156 * self.scale,
-            pos.y * 0.156 * self.scale,
-            pos.z * 0.156 * self.scale,
+            pos.x * 0.156 * self.params.scale,
+            pos.y * 0.156 * self.params.scale,
+            pos.z * 0.156 * self.params.scale,
         ]) + 1.)
             / 2.
-            * self.noise_threshold;
-        noise_v *= 10. * self.scale;
+            * self.params.noise_threshold;
+        noise_v *= 10. * self.params.scale;
 
         return noise_v;
     }
 
-    fn randomize_color(&self, pos: Vec3<f64>, color: [f32; 3], factor: f64) -> [f32; 3] {
-        let dr = self.simplex.get([pos.x / 2.3, pos.y / 2.3, pos.z / 2.3]) * factor;
-        let dg = self.simplex.get([-pos.x / 2.3, pos.y / 2.3, pos.z / 2.3]) * factor;
-        let db = self.simplex.get([pos.x / 2.3, -pos.y / 2.3, pos.z / 2.3]) * factor;
-        [
-            (color[0] + dr as f32).max(0.).min(1.),
-            (color[1] + dg as f32).max(0.).min(1.),
-            (color[2] + db as f32).max(0.).min(1.),
-        ]
+    /// How much each biome, in [`Biome::iter`] order, shapes the column at
+    /// `x`, `z`. The weights add up to 1.
+    fn get_biome_weights(&self, x: f64, z: f64) -> BiomeWeights {
+        let point = [x * CLIMATE_FREQUENCY, z * CLIMATE_FREQUENCY];
+        let temperature = self.temperature.get(point);
+        let humidity = self.humidity.get(point);
+
+        let mut weights = [0.; Biome::COUNT];
+        for (weight, biome) in weights.iter_mut().zip(Biome::iter()) {
+            let (biome_temperature, biome_humidity) = biome.get_params().climate;
+            let sq_distance =
+                (temperature - biome_temperature).powi(2) + (humidity - biome_humidity).powi(2);
+            *weight = (-sq_distance / (BIOME_BLEND_WIDTH * BIOME_BLEND_WIDTH)).exp();
+        }
+
+        let total: f64 = weights.iter().sum();
+        for weight in weights.iter_mut() {
+            *weight /= total;
+        }
+        weights
+    }
+
+    fn get_biome_height(&self, biome: Biome, x: f64, z: f64) -> f64 {
+        let params = biome.get_params();
+        let mut noise_v = self
+            .simplex
+            .get([x * params.frequency, z * params.frequency]);
+        if params.ridged {
+            noise_v = 1. - 2. * noise_v.abs();
+        }
+        params.base_height + params.amplitude * noise_v
     }
 
-    pub fn generate_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
+    /// Materials can't be blended, so they are dithered instead: the column
+    /// takes the materials of a biome picked at random with the biome weights
+    /// as odds. The random value only depends on the seed and the column.
+    fn pick_material_biome(&self, weights: &BiomeWeights, x: f64, z: f64) -> Biome {
+        let column_pos = Position::new(x.floor() as i64, 0, z.floor() as i64);
+        let mut pick = Random::new(self.params.seed, column_pos).next_f64();
+
+        for (weight, biome) in weights.iter().zip(Biome::iter()) {
+            if pick < *weight {
+                return biome;
+            }
+            pick -= weight;
+        }
+        // rounding left the pick past the last weight
+        Biome::iter().next_back().unwrap()
+    }
+
+    fn get_column(&self, x: f64, z: f64) -> Column {
+        let weights = self.get_biome_weights(x, z);
+        let mut column = Column {
+            surface: 0.,
+            cliffs: 0.,
+            material_biome: self.pick_material_biome(&weights, x, z),
+        };
+
+        for (weight, biome) in weights.iter().zip(Biome::iter()) {
+            column.surface += weight * self.get_biome_height(biome, x, z);
+            column.cliffs += weight * biome.get_params().cliffs;
+        }
+        column
+    }
+
+    fn get_biome_voxel(&self, column: &Column, pos: Vec3<f64>) -> Voxel {
+        let depth = column.surface - pos.y;
+
+        let mut level =
+            depth + self.params.noise_threshold - (self.simplex.get([pos.x, pos.z]) + 1.) * 0.001;
+        level /= 100. / self.params.scale;
+
+        let carved = self.get_cliffs_val(pos).min(level);
+        let value = level + (carved - level) * column.cliffs;
+
+        let params = column.material_biome.get_params();
+        let material = if depth < params.surface_depth {
+            params.surface_material
+        } else {
+            params.ground_material
+        };
+
+        Voxel {
+            material,
+            value: value.clamp(-0.1, 1.) as f32,
+        }
+    }
+
+    fn generate_hill_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
         for x in 0..size {
             for z in 0..size {
                 for y in 0..size {
@@ -76,10 +266,10 @@ impl Generator {
 
                     let level = self.get_level_val(pos);
 
-                    let mut color = [0.4; 3];
+                    let mut material = self.params.rock_material;
 
-                    if level < 0.03 * self.scale {
-                        color = [0.2, 0.7, 0.3];
+                    if level < self.params.grass_level * self.params.scale {
+                        material = self.params.grass_material;
                     }
 
                     let mut noise_v = self.get_cliffs_val(pos);
@@ -87,11 +277,59 @@ impl Generator {
                     noise_v = noise_v.min(level);
 
                     voxels[x + y * size + z * size * size] = Voxel {
-                        color: self.randomize_color(pos, color, 0.05),
+                        material,
                         value: noise_v.max(-0.1).min(1.) as f32,
                     };
                 }
             }
         }
     }
+
+    fn generate_biome_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
+        for x in 0..size {
+            for z in 0..size {
+                let column = self.get_column(offset.x + x as f64, offset.z + z as f64);
+
+                for y in 0..size {
+                    let pos = Vec3::new(
+                        offset.x + x as f64,
+                        offset.y + y as f64,
+                        offset.z + z as f64,
+                    );
+                    voxels[x + y * size + z * size * size] = self.get_biome_voxel(&column, pos);
+                }
+            }
+        }
+    }
+}
+
+impl TerrainGenerator for Generator {
+    fn generate_voxels(&self, offset: Vec3<f64>, voxels: &mut [Voxel], size: usize) {
+        if self.params.biomes {
+            self.generate_biome_voxels(offset, voxels, size);
+        } else {
+            self.generate_hill_voxels(offset, voxels, size);
+        }
+
+        if let Some(caves) = &self.caves {
+            caves.carve(offset, voxels, size);
+        }
+    }
+
+    fn get_biome(&self, pos: Position) -> Option<Biome> {
+        if !self.params.biomes {
+            return None;
+        }
+
+        let weights = self.get_biome_weights(pos.x as f64, pos.z as f64);
+        weights
+            .iter()
+            .zip(Biome::iter())
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, biome)| biome)
+    }
+
+    fn get_color_variation(&self) -> Option<f64> {
+        Some(self.params.color_variation)
+    }
 }
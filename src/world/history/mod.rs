@@ -0,0 +1,165 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    mem,
+};
+
+use crate::utils::position::Position;
+
+use super::voxel::Voxel;
+
+/// Default memory budget of an [`EditHistory`], in bytes.
+pub const DEFAULT_HISTORY_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Voxels changed by one edit stroke, with their value before the first and
+/// after the last change of the stroke.
+#[derive(Default)]
+pub struct Stroke {
+    voxels: BTreeMap<Position, (Voxel, Voxel)>,
+}
+
+impl Stroke {
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    /// Approximate memory used by the stroke, in bytes.
+    pub fn get_memory_size(&self) -> usize {
+        self.voxels.len() * (mem::size_of::<Position>() + 2 * mem::size_of::<Voxel>())
+    }
+
+    fn get_before(&self) -> Vec<(Position, Voxel)> {
+        self.voxels
+            .iter()
+            .map(|(pos, (before, _))| (*pos, *before))
+            .collect()
+    }
+
+    fn get_after(&self) -> Vec<(Position, Voxel)> {
+        self.voxels
+            .iter()
+            .map(|(pos, (_, after))| (*pos, *after))
+            .collect()
+    }
+}
+
+/// Undo/redo journal of terrain edits. Edits are recorded into the open
+/// stroke, usually one per mouse press, and undone a stroke at a time.
+///
+/// When the recorded strokes use more than the memory limit the oldest ones
+/// are forgotten.
+pub struct EditHistory {
+    undo: VecDeque<Stroke>,
+    /// Undone strokes, the next one to redo is last.
+    redo: Vec<Stroke>,
+    current: Option<Stroke>,
+
+    memory_limit: usize,
+    memory_used: usize,
+}
+
+impl EditHistory {
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            current: None,
+            memory_limit,
+            memory_used: 0,
+        }
+    }
+
+    pub fn get_memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn set_memory_limit(&mut self, memory_limit: usize) {
+        self.memory_limit = memory_limit;
+        self.trim();
+    }
+
+    pub fn get_memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    pub fn get_undo_count(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn get_redo_count(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Starts a new stroke, does nothing if one is already open.
+    pub fn begin_stroke(&mut self) {
+        if self.current.is_none() {
+            self.current = Some(Stroke::default());
+        }
+    }
+
+    /// Closes the open stroke and makes it the next one to undo.
+    pub fn end_stroke(&mut self) {
+        let stroke = match self.current.take() {
+            Some(stroke) if !stroke.is_empty() => stroke,
+            _ => return,
+        };
+
+        for stroke in mem::take(&mut self.redo) {
+            self.memory_used -= stroke.get_memory_size();
+        }
+
+        self.memory_used += stroke.get_memory_size();
+        self.undo.push_back(stroke);
+        self.trim();
+    }
+
+    /// Records a change of the voxel at `pos` into the open stroke.
+    pub fn record(&mut self, pos: Position, before: Voxel, after: Voxel) {
+        if let Some(stroke) = self.current.as_mut() {
+            stroke.voxels.entry(pos).or_insert((before, after)).1 = after;
+        }
+    }
+
+    /// Closes the open stroke and takes back the last one. Returns the voxels
+    /// to write to revert it.
+    pub fn undo(&mut self) -> Option<Vec<(Position, Voxel)>> {
+        self.end_stroke();
+        let stroke = self.undo.pop_back()?;
+        let voxels = stroke.get_before();
+        self.redo.push(stroke);
+        Some(voxels)
+    }
+
+    /// Returns the voxels to write to reapply the last undone stroke.
+    pub fn redo(&mut self) -> Option<Vec<(Position, Voxel)>> {
+        self.end_stroke();
+        let stroke = self.redo.pop()?;
+        let voxels = stroke.get_after();
+        self.undo.push_back(stroke);
+        Some(voxels)
+    }
+
+    fn trim(&mut self) {
+        while self.memory_used > self.memory_limit {
+            let stroke = match self.undo.pop_front() {
+                Some(stroke) => stroke,
+                None if !self.redo.is_empty() => self.redo.remove(0),
+                None => break,
+            };
+            self.memory_used -= stroke.get_memory_size();
+        }
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_MEMORY)
+    }
+}
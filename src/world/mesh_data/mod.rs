@@ -0,0 +1,53 @@
+use std::mem;
+
+/// Triangle mesh of a chunk kept on the CPU, independent of any GPU device.
+///
+/// Vertex attributes are stored in separate arrays of equal length. Without
+/// `indices` every three consecutive vertices form a triangle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub colors: Vec<[f32; 3]>,
+    pub indices: Option<Vec<u32>>,
+}
+
+impl MeshData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_vertex(&mut self, position: [f32; 3], normal: [f32; 3], color: [f32; 3]) {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.colors.push(color);
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        match &self.indices {
+            Some(indices) => indices.len() / 3,
+            None => self.positions.len() / 3,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangle_count() == 0
+    }
+
+    /// Bytes of the vertex and index arrays, as uploaded to the GPU.
+    pub fn get_byte_size(&self) -> usize {
+        let vertex_size = mem::size_of::<[f32; 3]>() * 3;
+        let index_size = self.indices.as_ref().map_or(0, |indices| indices.len());
+        self.vertex_count() * vertex_size + index_size * mem::size_of::<u32>()
+    }
+
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        for p in self.positions.iter_mut() {
+            *p = [p[0] + offset[0], p[1] + offset[1], p[2] + offset[2]];
+        }
+    }
+}
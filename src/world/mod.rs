@@ -1,383 +1,249 @@
-use crate::app_state::game_state::graphics::vertex::Vertex;
-use std::{
-    collections::{self, BTreeMap},
-    sync::Arc,
-    thread::{self, JoinHandle},
-};
+use std::{fs, io};
 
-use wgpu::{include_wgsl, RenderPass, RenderPipeline};
+use wgpu::RenderPass;
 use winit::window::Window;
 
 use crate::{
-    app_state::game_state::{input::InputKey, GameSate},
+    app_state::game_state::{
+        input::{InputKey, InputState},
+        GameSate,
+    },
     player::Player,
-    sun::Sun,
-    texture,
-    utils::position::{Position, PositionAroundIterator},
-    vec::Vec3,
+    utils::position::Position,
 };
 
 use self::{
-    chunk::{Chunk, CHUNK_REAL_SIZE, CHUNK_VOXELS_SIZE},
-    generator::Generator,
+    core::{MemoryStats, WorldCore},
+    edit_log::{EditLog, EDIT_LOG_FILE},
+    generator::{
+        graph::{self, GraphGenerator},
+        Generator, GeneratorParams, TerrainGenerator,
+    },
+    region::{RegionStore, SaveMode, WorldMeta},
+    renderer::WorldRenderer,
     voxel::Voxel,
 };
 
+pub mod brush;
 pub mod chunk;
+pub mod core;
+pub mod edit_log;
 pub mod generator;
+pub mod history;
+pub mod mesh_data;
+pub mod raycast;
+pub mod region;
+pub mod renderer;
 pub mod voxel;
 
-pub struct World {
-    chunks: collections::BTreeMap<Position, Chunk>,
-    render_pipeline: RenderPipeline,
-    pub player: Player,
-
-    chunk_generating_per_frame: usize,
-    render_distance: usize,
-    prev_player_chunk: Position,
-    chunk_load_iterator: PositionAroundIterator,
+const WORLD_SAVE_PATH: &str = "world";
+/// Optional terrain graph (see [`generator::graph`]) inside the save directory.
+pub const TERRAIN_GRAPH_FILE: &str = "terrain.ron";
 
-    generation_enabled: bool,
-    generator: Arc<Generator>,
-
-    sun: Sun,
+/// Ties a [`WorldCore`] to the window: feeds it player input and draws it
+/// through a [`WorldRenderer`].
+pub struct World {
+    pub core: WorldCore,
+    renderer: WorldRenderer,
 }
 
 impl World {
-    pub fn new(window: &Window, game_state: &GameSate) -> Self {
-        let screen_size = window.inner_size();
-        let shader = game_state
-            .game_graphics
-            .device
-            .create_shader_module(&include_wgsl!("shaders/main.wgsl"));
-
-        let sun = Sun::new(&game_state.game_graphics.device);
-
-        let player = Player::new(
-            &game_state.game_graphics.device,
-            (screen_size.width as f32, screen_size.height as f32),
-        );
-
-        let render_pipeline_layout = game_state.game_graphics.device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[player.get_bind_group_layout(), sun.get_bind_group_layout()],
-                push_constant_ranges: &[],
-            },
-        );
-
-        let render_pipeline = game_state.game_graphics.device.create_render_pipeline(
-            &wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[Vertex::get_description()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[wgpu::ColorTargetState {
-                        format: game_state.game_graphics.config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: texture::Texture::DEPTH_FORMAT,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less, // 1.
-                    stencil: wgpu::StencilState::default(),     // 2.
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            },
-        );
-
-        let chunks = BTreeMap::new();
-
-        let render_distance = 8;
-
-        return World {
-            sun,
-            chunks,
-            chunk_generating_per_frame: 4,
-            render_distance,
-            render_pipeline,
-            player,
-            generation_enabled: true,
-            prev_player_chunk: Position::new(0, 0, 0),
-            chunk_load_iterator: Position::new(0, 0, 0).iter_around(render_distance),
-            generator: Arc::new(Generator::new()),
-        };
+    /// Opens the saved world along with the inventory of its player, or
+    /// creates it. Fails when the terrain of the saved world can't be
+    /// restored, since its deltas would be applied to the wrong terrain.
+    pub fn new(window: &Window, game_state: &GameSate) -> io::Result<Self> {
+        let store = RegionStore::new(WORLD_SAVE_PATH, SaveMode::Delta);
+        let generator = Self::open_generator(&store)?;
+        let inventory = store.load_inventory()?;
+
+        let mut world = Self::with_generator(window, game_state, generator, store);
+        if let Some(inventory) = inventory {
+            world.core.player.inventory = inventory;
+        }
+        Ok(world)
     }
 
-    fn get_max_chunk(&self) -> usize {
-        (self.render_distance * 2 + 1).pow(3)
-    }
+    /// Creates a world on top of the terrain of `generator`. Chunks saved in
+    /// `store` must have been made with the same generator.
+    pub fn with_generator(
+        window: &Window,
+        game_state: &GameSate,
+        generator: Box<dyn TerrainGenerator>,
+        store: RegionStore,
+    ) -> Self {
+        let screen_size = window.inner_size();
 
-    fn load_chunk(&mut self, game_state: &mut GameSate) -> bool {
-        if self.chunks.len() >= self.get_max_chunk() {
-            return false;
-        }
+        let player = Player::new((screen_size.width as f32, screen_size.height as f32));
 
-        let camera_pos = self.player.get_pos();
-        let player_chunk_pos = Position::new(
-            (camera_pos.x / CHUNK_VOXELS_SIZE as f32) as i64,
-            (camera_pos.y / CHUNK_VOXELS_SIZE as f32) as i64,
-            (camera_pos.z / CHUNK_VOXELS_SIZE as f32) as i64,
-        );
+        let edit_log = EditLog::open(store.get_path().join(EDIT_LOG_FILE));
 
-        if self.prev_player_chunk != player_chunk_pos {
-            self.prev_player_chunk = player_chunk_pos;
-            self.chunk_load_iterator = player_chunk_pos.iter_around(self.render_distance);
+        let mut core = WorldCore::new(generator, store, player);
+        match edit_log {
+            Ok(edit_log) => core.set_edit_log(edit_log),
+            Err(err) => log::error!("failed to open edit log: {}", err),
         }
-
-        let max_chunks = self.chunk_generating_per_frame;
-        let mut chunk_generated: Vec<Position> = vec![];
-
-        let mut handles: Vec<JoinHandle<Chunk>> = vec![];
-
-        for p in self.chunk_load_iterator {
-            if self.chunks.get(&p).is_none() {
-                let np = p.clone();
-                let gen = self.generator.clone();
-                let device = game_state.game_graphics.device.clone();
-
-                handles.push(thread::spawn(move || {
-                    let mut new_chunk = Chunk::new(np);
-                    new_chunk.generate(&gen, &device);
-                    return new_chunk;
-                }));
-
-                chunk_generated.push(p.clone());
-                if chunk_generated.len() >= max_chunks {
-                    break;
+        let renderer = WorldRenderer::new(&game_state.game_graphics, &core);
+
+        return World { core, renderer };
+    }
+
+    /// Restores the generator of a saved world, so stored deltas apply to the
+    /// same terrain they were diffed against. A terrain graph in the save
+    /// directory replaces the built-in terrain, and can't be edited once the
+    /// world is created.
+    pub fn open_generator(store: &RegionStore) -> io::Result<Box<dyn TerrainGenerator>> {
+        let world_meta = store.load_meta()?;
+
+        let graph_path = store.get_path().join(TERRAIN_GRAPH_FILE);
+        if graph_path.exists() {
+            let source = fs::read_to_string(&graph_path)?;
+            let generator = GraphGenerator::from_ron(&source)?;
+            let terrain_hash = Some(graph::hash_source(&source));
+            match world_meta {
+                Some(world_meta) if world_meta.terrain_hash != terrain_hash => {
+                    return Err(invalid_data(
+                        "terrain graph differs from the one the world was created with",
+                    ));
                 }
+                Some(_) => {}
+                None => store.save_meta(&WorldMeta {
+                    params: GeneratorParams::default(),
+                    terrain_hash,
+                })?,
             }
+            return Ok(Box::new(generator));
         }
 
-        for h in handles {
-            let chunk = h.join().unwrap();
-            self.chunks.insert(chunk.get_position(), chunk);
-        }
-
-        return chunk_generated.len() > 0;
-    }
-
-    fn get_chunk_to_unload(&mut self) -> Option<Position> {
-        for (chunk_pos, _chunk) in self.chunks.iter() {
-            let player_pos = self.player.get_chunk_pos();
-            let delta = player_pos - chunk_pos.clone();
-
-            if delta.x.abs().max(delta.y.abs()).max(delta.z.abs()) > self.render_distance as i64 + 1
-            {
-                // println!("chunk at {:?} too far and will be unloaded", chunk_pos);
-                return Some(chunk_pos.clone());
-            }
-        }
-        return None;
-    }
-
-    fn unload_chunk(&mut self, _game_state: &mut GameSate) -> bool {
-        let chunk_to_unload = self.get_chunk_to_unload();
-        match chunk_to_unload {
-            Some(pos) => {
-                self.chunks.remove(&pos);
-                return true;
-            }
+        match world_meta {
+            Some(WorldMeta {
+                terrain_hash: Some(_),
+                ..
+            }) => Err(invalid_data("terrain graph of the world is missing")),
+            Some(world_meta) => Ok(Box::new(Generator::from_params(world_meta.params))),
             None => {
-                return false;
+                let params = GeneratorParams {
+                    biomes: true,
+                    caves: true,
+                    ..GeneratorParams::default()
+                };
+                store.save_meta(&WorldMeta {
+                    params,
+                    terrain_hash: None,
+                })?;
+                Ok(Box::new(Generator::from_params(params)))
             }
         }
     }
 
-    pub fn cast_ray(
-        &self,
-        from: Vec3<f32>,
-        dir: Vec3<f32>,
-        max_dist: f32,
-    ) -> Option<(Position, Voxel)> {
-        let step_size: f32 = 0.01;
-        let mut ray_len: f32 = 0.;
-
-        let mut chunk = self.get_chunk_by_vec(from)?;
-        println!("start {:?} {:?} {:?}", from, dir, chunk.get_position());
-
-        while ray_len < max_dist {
-            let pos = from + dir.mul_scalar(ray_len);
-
-            let in_chunk_pos =
-                Chunk::get_in_chunk_pos(Position::new(pos.x as i64, pos.y as i64, pos.z as i64));
-            let voxel = chunk.get_voxel(in_chunk_pos)?;
-
-            if voxel.value > 0. {
-                println!("{} {:?} {:?}", ray_len, in_chunk_pos, pos);
-                let result_pos =
-                    in_chunk_pos + chunk.get_position().mul_scalar(CHUNK_REAL_SIZE as i64);
-                return Some((result_pos, voxel));
-            }
-
-            let new_chunk_pos = Self::get_chunk_cord_by_vec(pos);
-
-            if new_chunk_pos != chunk.get_position() {
-                chunk = self.get_chunk(new_chunk_pos)?;
-            };
-
-            ray_len += step_size;
-        }
+    /// Writes every modified chunk that is still loaded to the region store.
+    pub fn save(&mut self) -> io::Result<()> {
+        self.core.save()
+    }
 
-        None
+    /// See [`WorldCore::get_memory_stats`].
+    pub fn get_memory_stats(&self) -> MemoryStats {
+        self.core.get_memory_stats()
     }
 
-    pub fn mine(&mut self, game_state: &mut GameSate) -> Option<()> {
-        let dt = game_state.game_time.get_delta_time();
-        let pos = self.player.get_pos();
-        let dir = self.player.get_look_dir();
-
-        let pos = self.cast_ray(pos, dir, 32.)?.0;
-
-        let chunks_to_dig = Chunk::get_chunk_pos(pos).iter_neighbors(true);
-        for chunk_pos in chunks_to_dig {
-            let chunk = match self.get_chunk_mut(chunk_pos) {
-                Some(chunk) => chunk,
-                _ => {
-                    let mut chunk = Chunk::new(chunk_pos.clone());
-                    chunk.generate(&self.generator, &game_state.game_graphics.device);
-                    self.chunks.insert(chunk_pos.clone(), chunk);
-                    self.chunks.get_mut(&chunk_pos)?
-                }
-            };
-            let modified_voxels = chunk.dig(pos, 6., dt / 20.);
-            if modified_voxels > 0 {
-                chunk.update_mesh(&game_state.game_graphics.device);
-            }
-        }
-        return Some(());
+    /// See [`WorldCore::set_voxel`].
+    pub fn set_voxel(&mut self, pos: Position, voxel: Voxel) {
+        self.core.set_voxel(pos, voxel);
     }
 
-    pub fn fill(&mut self, game_state: &mut GameSate) -> Option<()> {
-        let dt = game_state.game_time.get_delta_time();
-        let pos = self.player.get_pos();
-        let dir = self.player.get_look_dir();
-
-        let (pos, voxel) = self.cast_ray(pos, dir, 32.)?;
-
-        let chunks_to_dig = Chunk::get_chunk_pos(pos).iter_neighbors(true);
-        for chunk_pos in chunks_to_dig {
-            let chunk = match self.get_chunk_mut(chunk_pos) {
-                Some(chunk) => chunk,
-                _ => {
-                    let mut chunk = Chunk::new(chunk_pos.clone());
-                    chunk.generate(&self.generator, &game_state.game_graphics.device);
-                    self.chunks.insert(chunk_pos.clone(), chunk);
-                    self.chunks.get_mut(&chunk_pos)?
-                }
-            };
-            let modified_voxels = chunk.fill(pos, 6., voxel, dt / 20.);
-            if modified_voxels > 0 {
-                chunk.update_mesh(&game_state.game_graphics.device);
-            }
-        }
-        return Some(());
+    /// See [`WorldCore::modify_voxels`].
+    pub fn modify_voxels<F>(&mut self, min: Position, max: Position, modify: F) -> usize
+    where
+        F: FnMut(Position, &mut Voxel) -> bool,
+    {
+        self.core.modify_voxels(min, max, modify)
     }
 
     pub fn process_input(&mut self, game_state: &mut GameSate) {
+        let dt = game_state.game_time.get_delta_time();
+
+        // every mouse press is one undo step
         if game_state.game_input.is_pressed(InputKey::Mine) {
-            self.mine(game_state);
+            self.core.begin_stroke();
+            self.core.use_brush(dt, false);
         } else if game_state.game_input.is_pressed(InputKey::Fill) {
-            self.fill(game_state);
+            self.core.begin_stroke();
+            self.core.use_brush(dt, true);
+        } else {
+            self.core.end_stroke();
         }
-    }
 
-    pub fn update(&mut self, game_state: &mut GameSate) {
-        self.player.update(game_state);
+        self.process_brush_input(game_state);
 
-        if self.generation_enabled {
-            self.load_chunk(game_state);
+        if game_state.game_input.is_just_pressed(InputKey::Undo) {
+            self.core.undo();
+        } else if game_state.game_input.is_just_pressed(InputKey::Redo) {
+            self.core.redo();
         }
 
-        self.process_input(game_state);
-
-        self.chunks.iter_mut().for_each(|(_pos, chunk)| {
-            chunk.update();
-        });
+        match game_state.game_input.get_input_state(InputKey::CursorFree) {
+            InputState::JustPressed => {
+                game_state.show_cursor();
+            }
+            InputState::JustReleased => {
+                game_state.hide_cursor();
+            }
+            _ => {}
+        }
 
         if game_state
             .game_input
             .is_just_pressed(InputKey::ChunkGeneration)
         {
-            self.generation_enabled = !self.generation_enabled;
-        }
-
-        self.player.update_uniform(&game_state.game_graphics.queue);
-        self.sun.update_uniform(&game_state.game_graphics.queue);
-
-        if self.generation_enabled {
-            self.unload_chunk(game_state);
+            self.core.toggle_generation();
         }
     }
 
-    pub fn draw<'a>(self: &'a Self, render_pass: &mut RenderPass<'a>) {
-        render_pass.set_pipeline(&self.render_pipeline);
+    /// The mouse wheel changes the radius of the brush, or its strength
+    /// while `BrushStrength` is held.
+    fn process_brush_input(&mut self, game_state: &mut GameSate) {
+        let input = &game_state.game_input;
+        let brush = &mut self.core.player.brush;
 
-        self.player.draw(render_pass);
-        render_pass.set_bind_group(1, self.sun.get_bind_group(), &[]);
+        let wheel = input.mouse.get_wheel_delta();
+        if wheel != 0. {
+            if input.is_pressed(InputKey::BrushStrength) {
+                brush.scale_strength(wheel);
+            } else {
+                brush.resize(wheel);
+            }
+        }
 
-        for (_pos, chunk) in self.chunks.iter() {
-            chunk.draw(render_pass);
+        if input.is_just_pressed(InputKey::NextBrushShape) {
+            brush.shape = brush.shape.get_next();
+        }
+        if input.is_just_pressed(InputKey::NextBrushFalloff) {
+            brush.falloff = brush.falloff.get_next();
+        }
+        if input.is_just_pressed(InputKey::NextBrushMode) {
+            brush.mode = brush.mode.get_next();
+        }
+        if input.is_just_pressed(InputKey::PickMaterial) {
+            self.core.pick_material();
         }
     }
 
-    pub fn get_chunk_cord_by_vec(vec: Vec3<f32>) -> Position {
-        let x = vec.x / CHUNK_REAL_SIZE as f32;
-        let y = vec.y / CHUNK_REAL_SIZE as f32;
-        let z = vec.z / CHUNK_REAL_SIZE as f32;
-        Position::new(
-            if x < 0. { x as i64 - 1 } else { x as i64 },
-            if y < 0. { y as i64 - 1 } else { y as i64 },
-            if z < 0. { z as i64 - 1 } else { z as i64 },
-        )
-    }
+    pub fn update(&mut self, game_state: &mut GameSate) {
+        let dt = game_state.game_time.get_delta_time();
+        self.core.player.update(&game_state.game_input, dt);
 
-    pub fn get_chunk_by_vec(&self, vec: Vec3<f32>) -> Option<&Chunk> {
-        self.chunks.get(&Self::get_chunk_cord_by_vec(vec))
-    }
+        self.process_input(game_state);
 
-    fn get_chunk_mut(&mut self, chunk_pos: Position) -> Option<&mut Chunk> {
-        self.chunks.get_mut(&chunk_pos)
-    }
+        self.core.update(dt);
 
-    pub fn get_chunk(&self, chunk_pos: Position) -> Option<&Chunk> {
-        self.chunks.get(&chunk_pos)
+        self.renderer
+            .update(&mut self.core, &game_state.game_graphics);
     }
 
-    pub fn get_voxel(&self, pos: Position) -> Option<Voxel> {
-        let chunk_pos = Chunk::get_chunk_pos(pos);
-
-        match self.get_chunk(chunk_pos) {
-            Some(chunk) => {
-                let in_chunk_pos = Chunk::get_in_chunk_pos(pos);
-                return chunk.get_voxel(in_chunk_pos);
-            }
-            None => {
-                return None;
-            }
-        };
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        self.renderer.draw(render_pass);
     }
 }
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,202 @@
+use crate::{utils::position::Position, vec::Vec3};
+
+use super::voxel::Voxel;
+
+/// Where a ray met the terrain surface.
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    /// Point on the isosurface, in world coordinates.
+    pub position: Vec3<f32>,
+    /// Lowest corner of the marching cubes cell containing `position`.
+    pub cell: Position,
+    /// Unit normal of the surface, pointing out of the terrain.
+    pub normal: Vec3<f32>,
+    /// Distance from the ray origin to `position`.
+    pub distance: f32,
+    /// Solid corner of `cell` closest to `position`.
+    pub voxel: Voxel,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RayCast {
+    pub hit: Option<RayHit>,
+    /// Whether the ray passed through cells of chunks that are not loaded,
+    /// those are treated as empty.
+    pub crossed_unloaded: bool,
+}
+
+/// Corners of a cell, indexed by `x + y * 2 + z * 4`.
+type CellCorners = [Voxel; 8];
+
+fn get_corner_offset(index: usize) -> Position {
+    Position::new(
+        index as i64 & 1,
+        (index as i64 >> 1) & 1,
+        (index as i64 >> 2) & 1,
+    )
+}
+
+fn get_cell_corners<F>(cell: Position, get_voxel: &F) -> Option<CellCorners>
+where
+    F: Fn(Position) -> Option<Voxel>,
+{
+    let mut corners = [Voxel {
+        value: 0.,
+        material: 0,
+    }; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        *corner = get_voxel(cell + get_corner_offset(i))?;
+    }
+    Some(corners)
+}
+
+/// Trilinear interpolation of the corner values at `local`, in `0..=1`.
+fn sample(corners: &CellCorners, local: Vec3<f32>) -> f32 {
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let value = |i: usize| corners[i].value;
+
+    let y0 = lerp(
+        lerp(value(0), value(1), local.x),
+        lerp(value(2), value(3), local.x),
+        local.y,
+    );
+    let y1 = lerp(
+        lerp(value(4), value(5), local.x),
+        lerp(value(6), value(7), local.x),
+        local.y,
+    );
+    lerp(y0, y1, local.z)
+}
+
+/// Gradient of [`sample`] at `local`.
+fn get_gradient(corners: &CellCorners, local: Vec3<f32>) -> Vec3<f32> {
+    let value = |x: usize, y: usize, z: usize| corners[x + y * 2 + z * 4].value;
+    let weight = |i: usize, t: f32| if i == 0 { 1. - t } else { t };
+
+    let mut gradient = Vec3::new(0., 0., 0.);
+    for a in 0..2 {
+        for b in 0..2 {
+            gradient.x +=
+                (value(1, a, b) - value(0, a, b)) * weight(a, local.y) * weight(b, local.z);
+            gradient.y +=
+                (value(a, 1, b) - value(a, 0, b)) * weight(a, local.x) * weight(b, local.z);
+            gradient.z +=
+                (value(a, b, 1) - value(a, b, 0)) * weight(a, local.x) * weight(b, local.y);
+        }
+    }
+    gradient
+}
+
+fn to_local(cell: Position, point: Vec3<f32>) -> Vec3<f32> {
+    let clamp = |v: f32| v.clamp(0., 1.);
+    Vec3::new(
+        clamp(point.x - cell.x as f32),
+        clamp(point.y - cell.y as f32),
+        clamp(point.z - cell.z as f32),
+    )
+}
+
+fn get_closest_solid(corners: &CellCorners, local: Vec3<f32>) -> Voxel {
+    let distance = |i: usize| {
+        let offset = get_corner_offset(i);
+        (Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32) - local).sq_length()
+    };
+
+    (0..8)
+        .filter(|i| corners[*i].value >= 0.)
+        .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+        .map(|i| corners[i])
+        .unwrap_or(corners[0])
+}
+
+/// Walks the cells pierced by the ray with the Amanatides–Woo traversal and
+/// stops at the first one where the interpolated voxel values cross the
+/// isosurface (value 0).
+///
+/// `get_voxel` returns the voxel at a global lattice position, `None` for
+/// unloaded chunks.
+pub fn cast_ray<F>(from: Vec3<f32>, dir: Vec3<f32>, max_dist: f32, get_voxel: F) -> RayCast
+where
+    F: Fn(Position) -> Option<Voxel>,
+{
+    let mut result = RayCast::default();
+    if dir.sq_length() == 0. {
+        return result;
+    }
+    let dir = dir.normalize();
+
+    let from_arr = from.to_arr();
+    let dir_arr = dir.to_arr();
+    let mut cell = [0i64; 3];
+    let mut step = [0i64; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        cell[axis] = from_arr[axis].floor() as i64;
+        if dir_arr[axis] > 0. {
+            step[axis] = 1;
+            t_delta[axis] = 1. / dir_arr[axis];
+            t_max[axis] = (cell[axis] as f32 + 1. - from_arr[axis]) * t_delta[axis];
+        } else if dir_arr[axis] < 0. {
+            step[axis] = -1;
+            t_delta[axis] = -1. / dir_arr[axis];
+            t_max[axis] = (from_arr[axis] - cell[axis] as f32) * t_delta[axis];
+        }
+    }
+
+    let mut t_enter = 0.;
+    let mut normal = -dir;
+
+    while t_enter <= max_dist {
+        let axis = (0..3)
+            .min_by(|a, b| t_max[*a].total_cmp(&t_max[*b]))
+            .unwrap();
+        let t_exit = t_max[axis].min(max_dist);
+        let cell_pos = Position::new(cell[0], cell[1], cell[2]);
+
+        match get_cell_corners(cell_pos, &get_voxel) {
+            Some(corners) => {
+                let v_enter = sample(&corners, to_local(cell_pos, from + dir * t_enter));
+                let v_exit = sample(&corners, to_local(cell_pos, from + dir * t_exit));
+
+                let hit_t = if v_enter >= 0. {
+                    Some(t_enter)
+                } else if v_exit >= 0. {
+                    Some(t_enter + (t_exit - t_enter) * v_enter / (v_enter - v_exit))
+                } else {
+                    None
+                };
+
+                if let Some(t) = hit_t {
+                    let position = from + dir * t;
+                    let local = to_local(cell_pos, position);
+                    let gradient = get_gradient(&corners, local);
+                    if gradient.sq_length() > 0. {
+                        normal = -gradient.normalize();
+                    }
+
+                    result.hit = Some(RayHit {
+                        position,
+                        cell: cell_pos,
+                        normal,
+                        distance: t,
+                        voxel: get_closest_solid(&corners, local),
+                    });
+                    return result;
+                }
+            }
+            None => result.crossed_unloaded = true,
+        }
+
+        cell[axis] += step[axis];
+        t_enter = t_max[axis];
+        t_max[axis] += t_delta[axis];
+
+        let mut face_normal = [0.; 3];
+        face_normal[axis] = -step[axis] as f32;
+        normal = face_normal.into();
+    }
+
+    result
+}
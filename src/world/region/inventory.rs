@@ -0,0 +1,50 @@
+use std::io;
+
+use crate::player::inventory::Inventory;
+
+use super::invalid_data;
+
+const INVENTORY_VERSION: u32 = 1;
+
+/// Key=value lines like the world meta, one `material=amount` line per
+/// carried material.
+pub(super) fn encode(inventory: &Inventory) -> String {
+    let mut data = format!("version={}\n", INVENTORY_VERSION);
+    if inventory.is_unlimited() {
+        data.push_str("unlimited=true\n");
+    }
+    for (material, amount) in inventory.iter() {
+        data.push_str(&format!("{}={}\n", material, amount));
+    }
+    data
+}
+
+pub(super) fn decode(data: &str) -> io::Result<Inventory> {
+    let mut inventory = Inventory::new();
+    let mut version = None;
+
+    for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid_data("malformed inventory line"))?;
+
+        match key.trim() {
+            "version" => version = Some(parse::<u32>(value)?),
+            "unlimited" if parse(value)? => inventory = Inventory::unlimited(),
+            "unlimited" => {}
+            material => inventory.add(parse(material)?, parse(value)?),
+        }
+    }
+
+    match version {
+        Some(INVENTORY_VERSION) => Ok(inventory),
+        _ => Err(invalid_data("unsupported inventory version")),
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> io::Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid_data("malformed inventory value"))
+}
@@ -0,0 +1,74 @@
+use std::io;
+
+use crate::world::generator::GeneratorParams;
+
+use super::{invalid_data, WorldMeta};
+
+const META_VERSION: u32 = 7;
+
+pub(super) fn encode(world_meta: &WorldMeta) -> String {
+    let params = &world_meta.params;
+    let mut data = format!(
+        "version={}\nseed={}\nscale={}\nnoise_threshold={}\ngrass_level={}\ngrass_material={}\nrock_material={}\ncolor_variation={}\nbiomes={}\ncaves={}\n",
+        META_VERSION,
+        params.seed,
+        params.scale,
+        params.noise_threshold,
+        params.grass_level,
+        params.grass_material,
+        params.rock_material,
+        params.color_variation,
+        params.biomes,
+        params.caves,
+    );
+    if let Some(hash) = world_meta.terrain_hash {
+        data.push_str(&format!("terrain_hash={:016x}\n", hash));
+    }
+    data
+}
+
+pub(super) fn decode(data: &str) -> io::Result<WorldMeta> {
+    let mut params = GeneratorParams::default();
+    let mut terrain_hash = None;
+    let mut version = None;
+
+    for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid_data("malformed world meta line"))?;
+
+        match key.trim() {
+            "version" => version = Some(parse::<u32>(value)?),
+            "seed" => params.seed = parse(value)?,
+            "scale" => params.scale = parse(value)?,
+            "noise_threshold" => params.noise_threshold = parse(value)?,
+            "grass_level" => params.grass_level = parse(value)?,
+            "grass_material" => params.grass_material = parse(value)?,
+            "rock_material" => params.rock_material = parse(value)?,
+            "color_variation" => params.color_variation = parse(value)?,
+            "biomes" => params.biomes = parse(value)?,
+            "caves" => params.caves = parse(value)?,
+            "terrain_hash" => {
+                let hash = u64::from_str_radix(value.trim(), 16)
+                    .map_err(|_| invalid_data("malformed world meta value"))?;
+                terrain_hash = Some(hash);
+            }
+            _ => return Err(invalid_data("unknown world meta key")),
+        }
+    }
+
+    match version {
+        Some(META_VERSION) => Ok(WorldMeta {
+            params,
+            terrain_hash,
+        }),
+        _ => Err(invalid_data("unsupported world meta version")),
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> io::Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid_data("malformed world meta value"))
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    player::inventory::Inventory,
+    utils::{position::Position, true_mod::true_mod},
+};
+
+use super::{
+    chunk::{Chunk, CHUNK_VOXELS_VOLUME},
+    generator::{GeneratorParams, TerrainGenerator},
+    voxel::Voxel,
+};
+
+mod inventory;
+mod meta;
+
+/// Number of chunks stored along each axis of a single region file.
+pub const REGION_SIZE: i64 = 32;
+
+const REGION_MAGIC: &[u8; 4] = b"ADVR";
+const REGION_VERSION: u32 = 3;
+
+const META_FILE: &str = "world.meta";
+const INVENTORY_FILE: &str = "inventory";
+
+const RECORD_FULL: u8 = 0;
+const RECORD_DELTA: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveMode {
+    /// Store every voxel of a chunk.
+    Full,
+    /// Store only the voxels that differ from the generated terrain.
+    Delta,
+}
+
+enum StoredChunk {
+    Full(Vec<Voxel>),
+    Delta(Vec<(u32, Voxel)>),
+}
+
+type RegionChunks = BTreeMap<Position, StoredChunk>;
+
+/// What a world was generated with, kept in a metadata file next to the
+/// regions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldMeta {
+    pub params: GeneratorParams,
+    /// [`hash_source`](super::generator::graph::hash_source) of the terrain
+    /// graph the world was generated from, `None` when the world uses the
+    /// built-in terrain of `params`.
+    pub terrain_hash: Option<u64>,
+}
+
+/// Stores modified chunks on disk, grouping them into region files of
+/// `REGION_SIZE`³ chunks each.
+///
+/// Delta records can only be replayed on top of the terrain they were diffed
+/// against, so what the terrain was generated with is kept in a metadata file
+/// next to the regions (see [`RegionStore::save_meta`]).
+pub struct RegionStore {
+    path: PathBuf,
+    mode: SaveMode,
+}
+
+impl RegionStore {
+    pub fn new<P: AsRef<Path>>(path: P, mode: SaveMode) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            mode,
+        }
+    }
+
+    pub fn get_mode(&self) -> SaveMode {
+        self.mode
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_region_pos(chunk_pos: Position) -> Position {
+        Position::new(
+            chunk_pos.x.div_euclid(REGION_SIZE),
+            chunk_pos.y.div_euclid(REGION_SIZE),
+            chunk_pos.z.div_euclid(REGION_SIZE),
+        )
+    }
+
+    pub fn get_in_region_pos(chunk_pos: Position) -> Position {
+        Position::new(
+            true_mod(chunk_pos.x, REGION_SIZE),
+            true_mod(chunk_pos.y, REGION_SIZE),
+            true_mod(chunk_pos.z, REGION_SIZE),
+        )
+    }
+
+    fn get_region_file(&self, region_pos: Position) -> PathBuf {
+        self.path.join(format!(
+            "{}.{}.{}.region",
+            region_pos.x, region_pos.y, region_pos.z
+        ))
+    }
+
+    /// Writes what the terrain the stored deltas are based on was generated
+    /// with.
+    pub fn save_meta(&self, world_meta: &WorldMeta) -> io::Result<()> {
+        fs::create_dir_all(&self.path)?;
+        fs::write(self.path.join(META_FILE), meta::encode(world_meta))
+    }
+
+    /// Reads the meta of a previously saved world, if any.
+    pub fn load_meta(&self) -> io::Result<Option<WorldMeta>> {
+        match fs::read_to_string(self.path.join(META_FILE)) {
+            Ok(data) => meta::decode(&data).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the inventory of the player next to the world meta, it holds
+    /// the density dug out of the stored chunks.
+    pub fn save_inventory(&self, inventory: &Inventory) -> io::Result<()> {
+        fs::create_dir_all(&self.path)?;
+        fs::write(self.path.join(INVENTORY_FILE), inventory::encode(inventory))
+    }
+
+    /// Reads the inventory saved with the world, if any.
+    pub fn load_inventory(&self) -> io::Result<Option<Inventory>> {
+        match fs::read_to_string(self.path.join(INVENTORY_FILE)) {
+            Ok(data) => inventory::decode(&data).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the voxels of `chunk` into its region file, replacing any
+    /// previously stored version of the same chunk.
+    pub fn save_chunk(&self, chunk: &Chunk, generator: &dyn TerrainGenerator) -> io::Result<()> {
+        self.save_chunks([chunk], generator)
+    }
+
+    /// Writes the voxels of every chunk like [`RegionStore::save_chunk`], but
+    /// reads and writes each region file only once. Every region is tried and
+    /// the first error is returned.
+    pub fn save_chunks<'a>(
+        &self,
+        chunks: impl IntoIterator<Item = &'a Chunk>,
+        generator: &dyn TerrainGenerator,
+    ) -> io::Result<()> {
+        let mut regions: BTreeMap<Position, Vec<&Chunk>> = BTreeMap::new();
+        for chunk in chunks {
+            let region_pos = Self::get_region_pos(chunk.get_position());
+            regions.entry(region_pos).or_default().push(chunk);
+        }
+
+        let mut result = Ok(());
+        for (region_pos, region_chunks) in regions {
+            let saved = self.read_region(region_pos).and_then(|stored| {
+                let mut stored = stored.unwrap_or_default();
+                for chunk in region_chunks {
+                    let record = match self.mode {
+                        SaveMode::Full => StoredChunk::Full(chunk.get_voxels().to_vec()),
+                        SaveMode::Delta => StoredChunk::Delta(get_delta(chunk, generator)),
+                    };
+                    stored.insert(Self::get_in_region_pos(chunk.get_position()), record);
+                }
+                self.write_region(region_pos, &stored)
+            });
+            if result.is_ok() {
+                result = saved;
+            }
+        }
+        result
+    }
+
+    /// Reads the stored voxels of `chunk` from its region file. Delta records
+    /// are replayed on top of freshly generated terrain.
+    ///
+    /// Returns `false` if the chunk was never saved.
+    pub fn load_chunk(
+        &self,
+        chunk: &mut Chunk,
+        generator: &dyn TerrainGenerator,
+    ) -> io::Result<bool> {
+        let chunk_pos = chunk.get_position();
+        let mut chunks = match self.read_region(Self::get_region_pos(chunk_pos))? {
+            Some(chunks) => chunks,
+            None => return Ok(false),
+        };
+
+        match chunks.remove(&Self::get_in_region_pos(chunk_pos)) {
+            Some(StoredChunk::Full(voxels)) => {
+                chunk.set_voxels(&voxels);
+                Ok(true)
+            }
+            Some(StoredChunk::Delta(delta)) => {
+                chunk.generate_voxels(generator);
+                for (index, voxel) in delta {
+                    let pos = Chunk::index_to_pos(index as usize);
+                    chunk.set_voxel(pos, voxel);
+                }
+                chunk.set_modified(false);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn read_region(&self, region_pos: Position) -> io::Result<Option<RegionChunks>> {
+        let mut file = match fs::File::open(self.get_region_file(region_pos)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+
+        decode_region(&data).map(Some)
+    }
+
+    fn write_region(&self, region_pos: Position, chunks: &RegionChunks) -> io::Result<()> {
+        fs::create_dir_all(&self.path)?;
+
+        let file_path = self.get_region_file(region_pos);
+        let tmp_path = file_path.with_extension("region.tmp");
+
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&encode_region(chunks))?;
+        file.sync_all()?;
+
+        fs::rename(tmp_path, file_path)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn is_same_voxel(a: &Voxel, b: &Voxel) -> bool {
+    a.value.to_bits() == b.value.to_bits() && a.material == b.material
+}
+
+fn get_delta(chunk: &Chunk, generator: &dyn TerrainGenerator) -> Vec<(u32, Voxel)> {
+    let mut base = Chunk::new(chunk.get_position());
+    base.generate_voxels(generator);
+
+    chunk
+        .get_voxels()
+        .iter()
+        .zip(base.get_voxels().iter())
+        .enumerate()
+        .filter(|(_, (voxel, base_voxel))| !is_same_voxel(voxel, base_voxel))
+        .map(|(index, (voxel, _))| (index as u32, *voxel))
+        .collect()
+}
+
+fn encode_voxel(data: &mut Vec<u8>, voxel: &Voxel) {
+    data.extend_from_slice(&voxel.value.to_le_bytes());
+    data.extend_from_slice(&voxel.material.to_le_bytes());
+}
+
+fn encode_region(chunks: &RegionChunks) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(REGION_MAGIC);
+    data.extend_from_slice(&REGION_VERSION.to_le_bytes());
+    data.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+
+    for (pos, stored) in chunks.iter() {
+        data.extend_from_slice(&[pos.x as u8, pos.y as u8, pos.z as u8]);
+        match stored {
+            StoredChunk::Full(voxels) => {
+                data.push(RECORD_FULL);
+                data.extend_from_slice(&(voxels.len() as u32).to_le_bytes());
+                for voxel in voxels.iter() {
+                    encode_voxel(&mut data, voxel);
+                }
+            }
+            StoredChunk::Delta(delta) => {
+                data.push(RECORD_DELTA);
+                data.extend_from_slice(&(delta.len() as u32).to_le_bytes());
+                for (index, voxel) in delta.iter() {
+                    data.extend_from_slice(&index.to_le_bytes());
+                    encode_voxel(&mut data, voxel);
+                }
+            }
+        }
+    }
+
+    data
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.offset + len > self.data.len() {
+            return Err(invalid_data("unexpected end of region file"));
+        }
+        let result = &self.data[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(result)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_voxel(&mut self) -> io::Result<Voxel> {
+        Ok(Voxel {
+            value: self.read_f32()?,
+            material: self.read_u32()?,
+        })
+    }
+}
+
+fn decode_region(data: &[u8]) -> io::Result<RegionChunks> {
+    let mut reader = Reader { data, offset: 0 };
+
+    if reader.take(REGION_MAGIC.len())? != REGION_MAGIC {
+        return Err(invalid_data("not a region file"));
+    }
+    match reader.read_u32()? {
+        REGION_VERSION => {}
+        version => {
+            return Err(invalid_data(&format!(
+                "unsupported region file version {}",
+                version
+            )))
+        }
+    }
+
+    let count = reader.read_u32()?;
+    let mut chunks = RegionChunks::new();
+
+    for _ in 0..count {
+        let pos = Position::new(
+            reader.read_u8()? as i64,
+            reader.read_u8()? as i64,
+            reader.read_u8()? as i64,
+        );
+
+        let kind = reader.read_u8()?;
+        let len = reader.read_u32()? as usize;
+        if len > CHUNK_VOXELS_VOLUME || (kind == RECORD_FULL && len != CHUNK_VOXELS_VOLUME) {
+            return Err(invalid_data("unexpected chunk size in region file"));
+        }
+
+        let stored = match kind {
+            RECORD_FULL => {
+                let mut voxels = Vec::with_capacity(len);
+                for _ in 0..len {
+                    voxels.push(reader.read_voxel()?);
+                }
+                StoredChunk::Full(voxels)
+            }
+            RECORD_DELTA => {
+                let mut delta = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let index = reader.read_u32()?;
+                    if index as usize >= CHUNK_VOXELS_VOLUME {
+                        return Err(invalid_data("voxel index out of chunk in region file"));
+                    }
+                    delta.push((index, reader.read_voxel()?));
+                }
+                StoredChunk::Delta(delta)
+            }
+            _ => return Err(invalid_data("unknown chunk record in region file")),
+        };
+
+        chunks.insert(pos, stored);
+    }
+
+    Ok(chunks)
+}
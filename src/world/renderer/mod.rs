@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use wgpu::{include_wgsl, RenderPass, RenderPipeline};
+
+use crate::{
+    app_state::game_state::graphics::{mesh::Mesh, vertex::Vertex, GameGraphics},
+    player::camera::Camera,
+    sun::Sun,
+    texture,
+    utils::position::Position,
+};
+
+use super::{
+    core::WorldCore,
+    voxel::voxels_to_vertex::{mesh_chunk_with, MesherSettings},
+};
+
+/// GPU side of the world: keeps one mesh per chunk of a [`WorldCore`] and
+/// rebuilds them when the core reports changes.
+pub struct WorldRenderer {
+    render_pipeline: RenderPipeline,
+    camera: Camera,
+    sun: Sun,
+    meshes: BTreeMap<Position, Mesh>,
+    pub mesher_settings: MesherSettings,
+}
+
+impl WorldRenderer {
+    pub fn new(graphics: &GameGraphics, core: &WorldCore) -> Self {
+        let device = &graphics.device;
+        let shader = device.create_shader_module(&include_wgsl!("../shaders/main.wgsl"));
+
+        let sun = Sun::new(device);
+        let camera = Camera::new(device, &core.player.cam_state);
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[camera.get_bind_group_layout(), sun.get_bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::get_description()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: graphics.config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less, // 1.
+                stencil: wgpu::StencilState::default(),     // 2.
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let mut mesher_settings = MesherSettings::default();
+        if let Some(color_variation) = core.get_generator().get_color_variation() {
+            mesher_settings.color_variation = color_variation as f32;
+        }
+
+        Self {
+            render_pipeline,
+            camera,
+            sun,
+            meshes: BTreeMap::new(),
+            mesher_settings,
+        }
+    }
+
+    /// Syncs chunk meshes and uniforms with the current state of `core`.
+    pub fn update(&mut self, core: &mut WorldCore, graphics: &GameGraphics) {
+        for pos in core.take_unloaded_chunks() {
+            if let Some(mesh) = self.meshes.remove(&pos) {
+                mesh.destroy();
+            }
+        }
+
+        for pos in core.take_dirty_chunks() {
+            let neighbourhood = match core.get_neighbourhood(pos) {
+                Some(neighbourhood) => neighbourhood,
+                None => continue,
+            };
+
+            let mesh_data =
+                mesh_chunk_with(&neighbourhood, core.get_materials(), self.mesher_settings);
+            let missing_neighbours = neighbourhood.get_missing_sampled();
+            core.set_missing_neighbours(pos, missing_neighbours);
+            core.set_mesh_bytes(pos, mesh_data.get_byte_size());
+
+            let mesh = Mesh::new(&mesh_data, &graphics.device);
+            if let Some(old_mesh) = self.meshes.insert(pos, mesh) {
+                old_mesh.destroy();
+            }
+        }
+
+        self.camera
+            .update_uniform(&graphics.queue, &core.player.cam_state);
+        self.sun.update_uniform(&graphics.queue);
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+
+        self.camera.draw(render_pass);
+        render_pass.set_bind_group(1, self.sun.get_bind_group(), &[]);
+
+        for mesh in self.meshes.values() {
+            mesh.draw(render_pass);
+        }
+    }
+}
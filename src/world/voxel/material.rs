@@ -0,0 +1,99 @@
+use super::VoxelId;
+
+pub const ROCK: VoxelId = 0;
+pub const GRASS: VoxelId = 1;
+pub const SAND: VoxelId = 2;
+pub const SANDSTONE: VoxelId = 3;
+pub const SNOW: VoxelId = 4;
+pub const SLATE: VoxelId = 5;
+pub const GRAVEL: VoxelId = 6;
+pub const GRANITE: VoxelId = 7;
+pub const BEDROCK: VoxelId = 8;
+
+/// Properties shared by every voxel of a kind of terrain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub color: [f32; 3],
+    /// How hard the material is to dig, 1 for rock. Digging is divided by it,
+    /// so it has to be positive.
+    pub hardness: f32,
+    pub diggable: bool,
+}
+
+impl Material {
+    pub fn new(name: &str, color: [f32; 3], hardness: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            hardness,
+            diggable: true,
+        }
+    }
+
+    pub fn undiggable(name: &str, color: [f32; 3]) -> Self {
+        Self {
+            diggable: false,
+            ..Self::new(name, color, 1.)
+        }
+    }
+}
+
+/// Materials by [`VoxelId`]. The default registry holds the built-in
+/// materials, with ids matching the constants of this module.
+#[derive(Clone, Debug)]
+pub struct MaterialRegistry {
+    materials: Vec<Material>,
+}
+
+impl MaterialRegistry {
+    pub fn new() -> Self {
+        Self { materials: vec![] }
+    }
+
+    /// Adds `material` and returns its id.
+    pub fn register(&mut self, material: Material) -> VoxelId {
+        self.materials.push(material);
+        (self.materials.len() - 1) as VoxelId
+    }
+
+    pub fn get(&self, id: VoxelId) -> Option<&Material> {
+        self.materials.get(id as usize)
+    }
+
+    pub fn get_id(&self, name: &str) -> Option<VoxelId> {
+        self.materials
+            .iter()
+            .position(|material| material.name == name)
+            .map(|id| id as VoxelId)
+    }
+
+    /// Color of the material `id`, magenta for unknown ids.
+    pub fn get_color(&self, id: VoxelId) -> [f32; 3] {
+        self.get(id).map_or([1., 0., 1.], |material| material.color)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+}
+
+impl Default for MaterialRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Material::new("rock", [0.4; 3], 1.));
+        registry.register(Material::new("grass", [0.2, 0.7, 0.3], 0.5));
+        registry.register(Material::new("sand", [0.86, 0.78, 0.5], 0.3));
+        registry.register(Material::new("sandstone", [0.75, 0.6, 0.4], 0.8));
+        registry.register(Material::new("snow", [0.92, 0.94, 0.96], 0.2));
+        registry.register(Material::new("slate", [0.5, 0.5, 0.55], 1.2));
+        registry.register(Material::new("gravel", [0.45, 0.45, 0.47], 0.6));
+        registry.register(Material::new("granite", [0.35, 0.33, 0.32], 1.5));
+        registry.register(Material::undiggable("bedrock", [0.15, 0.14, 0.16]));
+        registry
+    }
+}
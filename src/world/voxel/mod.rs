@@ -1,10 +1,86 @@
+use crate::utils::position::Position;
+
+use self::material::MaterialRegistry;
+
+pub mod material;
 pub mod voxel_data;
 pub mod voxels_to_vertex;
 
+/// Index of a [`material::Material`] in a [`material::MaterialRegistry`].
 pub type VoxelId = u32;
 
-#[derive(Clone, Copy)]
+/// Value of a voxel with nothing left in it. Edits never take a voxel below
+/// it, and lower values count as empty.
+pub const EMPTY_VALUE: f32 = -0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Voxel {
     pub value: f32,
-    pub color: [f32; 3],
+    pub material: VoxelId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditOutcome {
+    Changed,
+    /// There was nothing to do, like digging an empty voxel.
+    Unchanged,
+    /// The voxel is of an undiggable material.
+    Resisted,
+}
+
+/// Result of an edit of many voxels.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EditReport {
+    pub changed: usize,
+    /// World positions of the voxels that could not be dug.
+    pub resisted: Vec<Position>,
+}
+
+impl Voxel {
+    /// Density held by the voxel, its value above [`EMPTY_VALUE`]. This is
+    /// what digging gives and filling costs.
+    pub fn get_density(&self) -> f32 {
+        (self.value - EMPTY_VALUE).max(0.)
+    }
+
+    /// Lowers the value of a solid voxel by `strength`, returns whether it changed.
+    pub fn dig(&mut self, strength: f32) -> bool {
+        if self.value < 0. {
+            return false;
+        }
+        self.value -= strength;
+        self.value = self.value.max(EMPTY_VALUE);
+        true
+    }
+
+    /// Digs like [`Voxel::dig`] with `strength` divided by the hardness of
+    /// the material of the voxel. Unknown materials dig like rock.
+    pub fn dig_material(&mut self, strength: f32, materials: &MaterialRegistry) -> EditOutcome {
+        if self.value < 0. {
+            return EditOutcome::Unchanged;
+        }
+
+        let hardness = match materials.get(self.material) {
+            Some(material) if !material.diggable => return EditOutcome::Resisted,
+            Some(material) => material.hardness,
+            None => 1.,
+        };
+
+        self.dig(strength / hardness);
+        EditOutcome::Changed
+    }
+
+    /// Raises the value of an empty voxel by `strength` and makes it of
+    /// `material`, returns whether it changed. Values below [`EMPTY_VALUE`]
+    /// are raised to it first, so the density added is at most `strength`.
+    pub fn fill(&mut self, material: VoxelId, strength: f32) -> bool {
+        if self.value >= 0. {
+            return false;
+        }
+        self.value = self.value.max(EMPTY_VALUE);
+        self.value += strength;
+        self.value = self.value.min(1.0);
+        self.material = material;
+        true
+    }
 }
@@ -1,9 +1,17 @@
-use crate::world::Vertex;
-use crate::{utils::position::Position, vec::Vec3, world::chunk::Chunk};
+use noise::NoiseFn;
+
+use crate::{
+    utils::position::Position,
+    vec::Vec3,
+    world::{
+        chunk::{neighbourhood::ChunkNeighbourhood, Chunk, CHUNK_REAL_SIZE, CHUNK_VOXELS_VOLUME},
+        mesh_data::MeshData,
+    },
+};
 
 use self::triangulation_table::{get_index_by_voxels, TABLE};
 
-use super::Voxel;
+use super::{material::MaterialRegistry, Voxel, VoxelId};
 
 mod triangulation_table;
 
@@ -70,36 +78,36 @@ const NODES: [VertexNode; NODES_POS_COUNT] = [DN, DE, DS, DW, UN, UE, US, UW, NW
 type Nodes = [Voxel; NODES_POS_COUNT];
 type VoxelsBlock = [[[Voxel; 2]; 2]; 2];
 
-fn get_voxel(chunk: &Chunk, pos: Position) -> Voxel {
-    match chunk.get_voxel(pos) {
+fn get_voxel(neighbourhood: &ChunkNeighbourhood, pos: Position) -> Voxel {
+    match neighbourhood.get_voxel(pos) {
         Some(voxel) => voxel,
         _ => Voxel {
             value: 0.,
-            color: [0.; 3],
+            material: 0,
         },
     }
 }
 
-fn get_voxels_for_vertex(chunk: &Chunk, base_pos: Position) -> VoxelsBlock {
+fn get_voxels_for_vertex(neighbourhood: &ChunkNeighbourhood, base_pos: Position) -> VoxelsBlock {
     let voxels: [[[Voxel; 2]; 2]; 2] = [
         [
             [
-                get_voxel(chunk, base_pos + Position::new(0, 0, 0)),
-                get_voxel(chunk, base_pos + Position::new(0, 0, 1)),
+                get_voxel(neighbourhood, base_pos + Position::new(0, 0, 0)),
+                get_voxel(neighbourhood, base_pos + Position::new(0, 0, 1)),
             ],
             [
-                get_voxel(chunk, base_pos + Position::new(0, 1, 0)),
-                get_voxel(chunk, base_pos + Position::new(0, 1, 1)),
+                get_voxel(neighbourhood, base_pos + Position::new(0, 1, 0)),
+                get_voxel(neighbourhood, base_pos + Position::new(0, 1, 1)),
             ],
         ],
         [
             [
-                get_voxel(chunk, base_pos + Position::new(1, 0, 0)),
-                get_voxel(chunk, base_pos + Position::new(1, 0, 1)),
+                get_voxel(neighbourhood, base_pos + Position::new(1, 0, 0)),
+                get_voxel(neighbourhood, base_pos + Position::new(1, 0, 1)),
             ],
             [
-                get_voxel(chunk, base_pos + Position::new(1, 1, 0)),
-                get_voxel(chunk, base_pos + Position::new(1, 1, 1)),
+                get_voxel(neighbourhood, base_pos + Position::new(1, 1, 0)),
+                get_voxel(neighbourhood, base_pos + Position::new(1, 1, 1)),
             ],
         ],
     ];
@@ -109,26 +117,26 @@ fn get_voxels_for_vertex(chunk: &Chunk, base_pos: Position) -> VoxelsBlock {
 fn chose_voxel_for_node(a: Voxel, b: Voxel) -> Voxel {
     if a.value < 0. {
         return Voxel {
-            color: b.color,
+            material: b.material,
             value: (-a.value) / (b.value - a.value),
         };
     }
     if b.value < 0. {
         return Voxel {
-            color: a.color,
+            material: a.material,
             value: 1.0 - (-b.value) / (a.value - b.value),
         };
     }
     return Voxel {
         value: 0.,
-        color: [0.; 3],
+        material: 0,
     };
 }
 
 fn get_vertex_nodes(voxels: VoxelsBlock) -> Nodes {
     let mut result: Nodes = [Voxel {
         value: 0.,
-        color: [0.; 3],
+        material: 0,
     }; NODES_POS_COUNT];
 
     result[DS.index] = chose_voxel_for_node(voxels[0][0][0], voxels[1][0][0]);
@@ -163,63 +171,287 @@ fn shift_node_pos(pos: Vec3<f32>, value: f32) -> Vec3<f32> {
     panic!("failed to process pos {:?}", pos);
 }
 
-fn append_triangle(
-    pos: Position,
-    vertex: &mut Vec<Vertex>,
-    nodes: Nodes,
-    a: VertexNode,
-    b: VertexNode,
-    c: VertexNode,
-) {
-    let a_v = nodes[a.index];
-    let b_v = nodes[b.index];
-    let c_v = nodes[c.index];
-
-    if a_v.value < 0. || a_v.value < 0. || c_v.value < 0. {
-        return;
-    }
-
-    let pos_vec = Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
-
-    let a_pos = shift_node_pos(a.pos, a_v.value) + pos_vec;
-    let b_pos = shift_node_pos(b.pos, b_v.value) + pos_vec;
-    let c_pos = shift_node_pos(c.pos, c_v.value) + pos_vec;
-
-    let normal_vec = (c_pos - a_pos).cross(b_pos - a_pos).normalize();
-    let normal: [f32; 3] = [normal_vec.x, normal_vec.y, normal_vec.z];
-
-    vertex.push(Vertex {
-        color: a_v.color,
-        normal,
-        position: c_pos.to_arr(),
-    });
-    vertex.push(Vertex {
-        color: a_v.color,
-        normal,
-        position: b_pos.to_arr(),
-    });
-    vertex.push(Vertex {
-        color: a_v.color,
-        normal,
-        position: a_pos.to_arr(),
-    });
+/// Lattice corner and axis of the cell edge a node lies on, so that the
+/// neighbouring cells sharing the edge resolve it to the same vertex.
+fn get_node_edge(pos: Position, node: VertexNode) -> (Position, usize) {
+    let corner = Position::new(
+        pos.x + (node.pos.x == 1.0) as i64,
+        pos.y + (node.pos.y == 1.0) as i64,
+        pos.z + (node.pos.z == 1.0) as i64,
+    );
+    let axis = match node.pos {
+        p if p.x == 0.5 => 0,
+        p if p.y == 0.5 => 1,
+        p if p.z == 0.5 => 2,
+        p => panic!("failed to process pos {:?}", p),
+    };
+    (corner, axis)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shading {
+    /// One normal per triangle, vertices are not shared.
+    Flat,
+    /// Shared vertices with normals from the density gradient.
+    Smooth,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MesherSettings {
+    pub shading: Shading,
+    /// Amplitude of the noise added to the material colors.
+    pub color_variation: f32,
 }
 
-pub fn append_vertex(pos: Position, chunk: &Chunk, vertex: &mut Vec<Vertex>) {
-    let voxels = get_voxels_for_vertex(chunk, pos);
-    let nodes = get_vertex_nodes(voxels);
+impl Default for MesherSettings {
+    fn default() -> Self {
+        Self {
+            shading: Shading::Smooth,
+            color_variation: 0.05,
+        }
+    }
+}
+
+const NO_VERTEX: u32 = u32::MAX;
+
+struct ChunkMesher<'a> {
+    neighbourhood: &'a ChunkNeighbourhood<'a>,
+    materials: &'a MaterialRegistry,
+    settings: MesherSettings,
+    /// World position of the first voxel of the chunk.
+    origin: Vec3<f64>,
+    simplex: noise::OpenSimplex,
+    mesh: MeshData,
+    indices: Vec<u32>,
+    /// Vertex index of every cell edge already emitted, `NO_VERTEX` otherwise.
+    edge_vertex: Vec<u32>,
+}
+
+impl<'a> ChunkMesher<'a> {
+    fn new(
+        neighbourhood: &'a ChunkNeighbourhood<'a>,
+        materials: &'a MaterialRegistry,
+        settings: MesherSettings,
+    ) -> Self {
+        let origin = neighbourhood
+            .get_center()
+            .get_position()
+            .mul_scalar(CHUNK_REAL_SIZE as i64);
+
+        Self {
+            neighbourhood,
+            materials,
+            settings,
+            origin: Vec3::new(origin.x as f64, origin.y as f64, origin.z as f64),
+            simplex: noise::OpenSimplex::new(),
+            mesh: MeshData::new(),
+            indices: vec![],
+            edge_vertex: vec![NO_VERTEX; CHUNK_VOXELS_VOLUME * 3],
+        }
+    }
+
+    fn sample_value(&self, pos: Position) -> Option<f32> {
+        self.neighbourhood.get_voxel(pos).map(|voxel| voxel.value)
+    }
+
+    /// Central difference of the density at a lattice point, falling back to a
+    /// one-sided difference where a neighbouring chunk is not available.
+    fn get_gradient(&self, pos: Position) -> Vec3<f32> {
+        let center = self.sample_value(pos).unwrap_or(0.);
+        let mut gradient = [0.; 3];
+
+        for (axis, g) in gradient.iter_mut().enumerate() {
+            let mut step = Position::new(0, 0, 0);
+            match axis {
+                0 => step.x = 1,
+                1 => step.y = 1,
+                _ => step.z = 1,
+            }
+
+            *g = match (self.sample_value(pos + step), self.sample_value(pos - step)) {
+                (Some(next), Some(prev)) => (next - prev) / 2.,
+                (Some(next), None) => next - center,
+                (None, Some(prev)) => center - prev,
+                (None, None) => 0.,
+            };
+        }
+
+        gradient.into()
+    }
+
+    fn get_vertex_normal(&self, pos: Position, node: VertexNode, t: f32) -> [f32; 3] {
+        let (corner, axis) = get_node_edge(pos, node);
+        let mut other = corner;
+        match axis {
+            0 => other.x += 1,
+            1 => other.y += 1,
+            _ => other.z += 1,
+        }
+
+        let gradient = self.get_gradient(corner) * (1. - t) + self.get_gradient(other) * t;
+        let length = gradient.length();
+        if length > 0. {
+            // density grows into the terrain, so the surface faces the other way
+            (-gradient / length).to_arr()
+        } else {
+            [0.; 3]
+        }
+    }
+
+    /// Material color with some noise, so that large surfaces of the same
+    /// material are not flat.
+    fn get_color(&self, material: VoxelId, pos: Vec3<f32>) -> [f32; 3] {
+        let mut color = self.materials.get_color(material);
+        if self.settings.color_variation <= 0. {
+            return color;
+        }
+
+        let x = (self.origin.x + pos.x as f64) / 2.3;
+        let y = (self.origin.y + pos.y as f64) / 2.3;
+        let z = (self.origin.z + pos.z as f64) / 2.3;
+        let noise = [
+            self.simplex.get([x, y, z]),
+            self.simplex.get([-x, y, z]),
+            self.simplex.get([x, -y, z]),
+        ];
+
+        for (channel, noise) in color.iter_mut().zip(noise) {
+            *channel = (*channel + noise as f32 * self.settings.color_variation).clamp(0., 1.);
+        }
+        color
+    }
 
-    let triangle_points = TABLE[get_index_by_voxels(voxels)];
+    fn get_vertex(&mut self, pos: Position, nodes: &Nodes, node: VertexNode) -> u32 {
+        let (corner, axis) = get_node_edge(pos, node);
+        let edge = Chunk::pos_to_index(corner).unwrap() * 3 + axis;
 
-    let mut triangle_offset = 0;
+        if self.edge_vertex[edge] == NO_VERTEX {
+            let voxel = nodes[node.index];
+            let pos_vec = Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
+            let position = shift_node_pos(node.pos, voxel.value) + pos_vec;
+            let normal = self.get_vertex_normal(pos, node, voxel.value);
 
-    while triangle_points[triangle_offset] != -1 {
-        let a = NODES[triangle_points[triangle_offset] as usize];
-        let b = NODES[triangle_points[triangle_offset + 1] as usize];
-        let c = NODES[triangle_points[triangle_offset + 2] as usize];
+            let color = self.get_color(voxel.material, position);
 
-        append_triangle(pos, vertex, nodes, a, b, c);
+            self.edge_vertex[edge] = self.mesh.vertex_count() as u32;
+            self.mesh.push_vertex(position.to_arr(), normal, color);
+        }
 
-        triangle_offset += 3;
+        self.edge_vertex[edge]
     }
+
+    fn append_flat_triangle(
+        &mut self,
+        pos: Position,
+        nodes: &Nodes,
+        a: VertexNode,
+        b: VertexNode,
+        c: VertexNode,
+    ) {
+        let a_v = nodes[a.index];
+        let b_v = nodes[b.index];
+        let c_v = nodes[c.index];
+
+        let pos_vec = Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
+
+        let a_pos = shift_node_pos(a.pos, a_v.value) + pos_vec;
+        let b_pos = shift_node_pos(b.pos, b_v.value) + pos_vec;
+        let c_pos = shift_node_pos(c.pos, c_v.value) + pos_vec;
+
+        let normal = (c_pos - a_pos).cross(b_pos - a_pos).normalize().to_arr();
+
+        let color = self.get_color(a_v.material, a_pos);
+
+        self.mesh.push_vertex(c_pos.to_arr(), normal, color);
+        self.mesh.push_vertex(b_pos.to_arr(), normal, color);
+        self.mesh.push_vertex(a_pos.to_arr(), normal, color);
+    }
+
+    fn append_triangle(
+        &mut self,
+        pos: Position,
+        nodes: &Nodes,
+        a: VertexNode,
+        b: VertexNode,
+        c: VertexNode,
+    ) {
+        if nodes[a.index].value < 0. || nodes[b.index].value < 0. || nodes[c.index].value < 0. {
+            return;
+        }
+
+        if self.settings.shading == Shading::Flat {
+            self.append_flat_triangle(pos, nodes, a, b, c);
+            return;
+        }
+
+        let a_i = self.get_vertex(pos, nodes, a);
+        let b_i = self.get_vertex(pos, nodes, b);
+        let c_i = self.get_vertex(pos, nodes, c);
+
+        self.indices.extend_from_slice(&[c_i, b_i, a_i]);
+    }
+
+    fn append_cell(&mut self, pos: Position) {
+        let voxels = get_voxels_for_vertex(self.neighbourhood, pos);
+        let nodes = get_vertex_nodes(voxels);
+
+        let triangle_points = TABLE[get_index_by_voxels(voxels)];
+
+        let mut triangle_offset = 0;
+
+        while triangle_points[triangle_offset] != -1 {
+            let a = NODES[triangle_points[triangle_offset] as usize];
+            let b = NODES[triangle_points[triangle_offset + 1] as usize];
+            let c = NODES[triangle_points[triangle_offset + 2] as usize];
+
+            self.append_triangle(pos, &nodes, a, b, c);
+
+            triangle_offset += 3;
+        }
+    }
+
+    fn finish(mut self) -> MeshData {
+        if self.settings.shading == Shading::Smooth {
+            self.mesh.indices = Some(self.indices);
+        }
+        self.mesh
+    }
+}
+
+/// Builds the mesh of `chunk` in world coordinates with default settings and
+/// materials, without looking into neighbouring chunks.
+pub fn mesh_chunk(chunk: &Chunk) -> MeshData {
+    mesh_chunk_with(
+        &ChunkNeighbourhood::alone(chunk),
+        &MaterialRegistry::default(),
+        MesherSettings::default(),
+    )
+}
+
+/// Builds the mesh of the center chunk of `neighbourhood` in world
+/// coordinates, colored by `materials`.
+///
+/// With smooth shading vertices on cell edges shared by neighbouring cells
+/// are emitted once and indexed, with normals taken from the density
+/// gradient. Samples past the chunk border come from the neighbouring chunks
+/// and only fall back to an empty voxel where a neighbour is not loaded.
+pub fn mesh_chunk_with(
+    neighbourhood: &ChunkNeighbourhood,
+    materials: &MaterialRegistry,
+    settings: MesherSettings,
+) -> MeshData {
+    let mut mesher = ChunkMesher::new(neighbourhood, materials, settings);
+    for x in 0..CHUNK_REAL_SIZE {
+        for y in 0..CHUNK_REAL_SIZE {
+            for z in 0..CHUNK_REAL_SIZE {
+                mesher.append_cell(Position::new(x as i64, y as i64, z as i64));
+            }
+        }
+    }
+
+    let offset = mesher.origin;
+    let mut mesh = mesher.finish();
+    mesh.translate([offset.x as f32, offset.y as f32, offset.z as f32]);
+
+    mesh
 }